@@ -0,0 +1,65 @@
+use crate::math::{ConstMatrix, Vector, vector};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub eye: Vector,
+    pub aim: Vector,
+    pub up: Vector,
+    // vertical field of view in degrees
+    pub fov: f64,
+    pub near: f64,
+    pub far: f64,
+}
+
+impl Camera {
+    pub fn new(eye: Vector, aim: Vector) -> Self {
+        Self {
+            eye,
+            aim,
+            up: [0f64, 1f64, 0f64],
+            fov: 60f64,
+            near: 1f64,
+            far: 1000f64,
+        }
+    }
+
+    // moves the eye to the origin looking down -z, with up along +y
+    pub fn view_matrix(&self) -> ConstMatrix<f64, 4, 4> {
+        let f = vector::normalize(vector::subtract(self.aim, self.eye));
+        let s = vector::normalize(vector::cross(f, self.up));
+        let u = vector::cross(s, f);
+
+        let mut m = ConstMatrix::<f64, 4, 4>::identity();
+        for i in 0..3 {
+            m[(i, 0)] = s[i];
+            m[(i, 1)] = u[i];
+            m[(i, 2)] = -f[i];
+        }
+        m[(3, 0)] = -vector::dot(s, self.eye);
+        m[(3, 1)] = -vector::dot(u, self.eye);
+        m[(3, 2)] = vector::dot(f, self.eye);
+
+        m
+    }
+
+    // maps the view frustum onto the clip space cube, leaving the depth in w for the divide
+    pub fn projection_matrix(&self, aspect: f64) -> ConstMatrix<f64, 4, 4> {
+        let f = 1f64 / (self.fov.to_radians() / 2f64).tan();
+
+        let mut m = ConstMatrix::<f64, 4, 4>::default();
+        m[(0, 0)] = f / aspect;
+        m[(1, 1)] = f;
+        m[(2, 2)] = (self.far + self.near) / (self.near - self.far);
+        m[(3, 2)] = 2f64 * self.far * self.near / (self.near - self.far);
+        m[(2, 3)] = -1f64;
+
+        m
+    }
+
+    pub fn matrix(&self, aspect: f64) -> ConstMatrix<f64, 4, 4> {
+        let mut m = self.view_matrix();
+        m.multiply(&self.projection_matrix(aspect));
+
+        m
+    }
+}
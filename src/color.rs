@@ -1,24 +1,183 @@
 use std::fmt;
 
-// color(red, green, blue)
-#[derive(Copy, Clone, Debug)]
+// color(red, green, blue, alpha), alpha is straight rather than premultiplied
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
+    pub alpha: u8,
 }
 
+// colors are opaque unless given an alpha
 #[macro_export]
 macro_rules! new_color {
     ($red:expr, $green:expr, $blue:expr) => {
+        $crate::new_color!($red, $green, $blue, 255)
+    };
+    ($red:expr, $green:expr, $blue:expr, $alpha:expr) => {
         Color {
             red: $red, 
             green: $green, 
             blue: $blue,
+            alpha: $alpha,
         }
+    };
+}
+
+// how a color mixes with the one underneath before being laid over it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Add,
+}
+
+impl Color {
+    pub fn with_alpha(self, alpha: u8) -> Color {
+        Color { alpha, ..self }
+    }
+
+    // porter-duff source over destination, with the blend mode deciding what shows where both are present
+    pub fn over(self, dst: Color, mode: BlendMode) -> Color {
+        let unit = |c: Color| c.channels().map(|v| v / 255f64);
+        Color::from_channels(composite(unit(self), unit(dst), mode).map(|v| v * 255f64))
+    }
+
+    // red, green, blue and alpha on the same 0 to 255 scale as the bytes
+    pub fn channels(self) -> [f64; 4] {
+        [self.red, self.green, self.blue, self.alpha].map(|c| c as f64)
+    }
+
+    // rounds and clamps anything outside 0 to 255
+    pub fn from_channels(channels: [f64; 4]) -> Color {
+        let [r, g, b, a] = channels.map(|c| c.round().clamp(0f64, 255f64) as u8);
+        new_color!(r, g, b, a)
+    }
+
+    // #rgb, #rgba, #rrggbb or #rrggbbaa, a css color name, or hsv(h, s, v) and hsl(h, s, l)
+    // with the hue in degrees and the rest either between 0 and 1 or as percentages
+    pub fn parse(text: &str) -> Option<Color> {
+        let text = text.trim().to_ascii_lowercase();
+
+        if let Some(hex) = text.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some([h, s, v]) = arguments(&text, "hsv") {
+            return Some(Color::from_hsv(hue(h)?, fraction(s)?, fraction(v)?));
+        }
+        if let Some([h, s, l]) = arguments(&text, "hsl") {
+            return Some(Color::from_hsl(hue(h)?, fraction(s)?, fraction(l)?));
+        }
+        if text == "transparent" {
+            return Some(new_color!(0, 0, 0, 0));
+        }
+
+        NAMED_COLORS.binary_search_by(|(name, _)| name.cmp(&text.as_str()))
+            .ok()
+            .map(|i| from_rgb(NAMED_COLORS[i].1))
+    }
+
+    // hue in degrees, saturation and value between 0 and 1
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Color {
+        let chroma = value * saturation;
+        from_chroma(hue, chroma, value - chroma)
+    }
+
+    // hue in degrees, saturation and lightness between 0 and 1
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Color {
+        let chroma = (1f64 - (2f64 * lightness - 1f64).abs()) * saturation;
+        from_chroma(hue, chroma, lightness - chroma / 2f64)
     }
 }
 
+fn from_rgb(rgb: u32) -> Color {
+    new_color!((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        // every digit doubles up, so #f80 is #ff8800
+        3 | 4 => parse_hex(&hex.chars().flat_map(|c| [c, c]).collect::<String>()),
+        6 => Some(from_rgb(value)),
+        8 => Some(from_rgb(value >> 8).with_alpha(value as u8)),
+        _ => None,
+    }
+}
+
+// the three comma separated arguments of name(a, b, c)
+fn arguments<'a>(text: &'a str, name: &str) -> Option<[&'a str; 3]> {
+    let inner = text.strip_prefix(name)?.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+    let mut values = inner.split(',').map(str::trim);
+    let arguments = [values.next()?, values.next()?, values.next()?];
+
+    match values.next() {
+        Some(_) => None,
+        None => Some(arguments),
+    }
+}
+
+fn hue(text: &str) -> Option<f64> {
+    text.strip_suffix("deg").unwrap_or(text).trim().parse().ok()
+}
+
+fn fraction(text: &str) -> Option<f64> {
+    let value = match text.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100f64,
+        None => text.parse().ok()?,
+    };
+
+    (0f64..=1f64).contains(&value).then_some(value)
+}
+
+// the hue picks a side of the color wheel, chroma how far out from gray and offset how bright the gray is
+fn from_chroma(hue: f64, chroma: f64, offset: f64) -> Color {
+    let sector = hue.rem_euclid(360f64) / 60f64;
+    let x = chroma * (1f64 - (sector % 2f64 - 1f64).abs());
+
+    let [r, g, b] = match sector as u8 {
+        0 => [chroma, x, 0f64],
+        1 => [x, chroma, 0f64],
+        2 => [0f64, chroma, x],
+        3 => [0f64, x, chroma],
+        4 => [x, 0f64, chroma],
+        _ => [chroma, 0f64, x],
+    };
+
+    Color::from_channels([r, g, b, 1f64].map(|c| (c + offset) * 255f64).map(|c| c.min(255f64)))
+}
+
+// source over destination for straight alpha channels between 0 and 1, the colors themselves may run past 1
+pub fn composite(src: [f64; 4], dst: [f64; 4], mode: BlendMode) -> [f64; 4] {
+    let (src_alpha, dst_alpha) = (src[3], dst[3]);
+    let alpha = src_alpha + dst_alpha * (1f64 - src_alpha);
+    if alpha <= 0f64 {
+        return [0f64; 4];
+    }
+
+    let channel = |s: f64, d: f64| {
+        let blended = match mode {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => s * d,
+            BlendMode::Screen => s + d - s * d,
+            BlendMode::Add => s + d,
+        };
+        // where the destination is see-through the source keeps its own color
+        let s = (1f64 - dst_alpha) * s + dst_alpha * blended;
+
+        (src_alpha * s + dst_alpha * d * (1f64 - src_alpha)) / alpha
+    };
+
+    [channel(src[0], dst[0]), channel(src[1], dst[1]), channel(src[2], dst[2]), alpha]
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} {} {}", self.red, self.green, self.blue)
@@ -43,3 +202,155 @@ pub const COLOR_PURPLE: Color = new_color!(255, 0, 255);
 pub const COLOR_YELLOW: Color = new_color!(255, 255, 0);
 #[allow(dead_code)]
 pub const COLOR_PASTEL_YELLOW: Color = new_color!(239, 169, 74);
+
+// the css named colors, sorted by name
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use crate::color::*;
+use crate::image::Image;
+use crate::new_color;
+
+// how far one image is from another, with errors on the 0 to 255 scale of a channel
+#[derive(Clone)]
+pub struct Comparison {
+    pub max_error: u8,
+    pub mean_error: f64,
+    // in decibels, infinite for identical images
+    pub psnr: f64,
+    // pixels with any channel further off than the tolerance
+    pub mismatched: usize,
+    // the image faded to gray, with every mismatched pixel in red
+    pub diff: Image,
+}
+
+impl Comparison {
+    pub fn is_match(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} pixels differ, max error {}, mean error {:.4}, psnr {:.2} dB", self.mismatched, self.max_error, self.mean_error, self.psnr)
+    }
+}
+
+impl Image {
+    pub fn compare(&self, other: &Image) -> Comparison {
+        self.compare_within(other, 0)
+    }
+
+    // alpha counts as a channel like the others; both images have to be the same size
+    pub fn compare_within(&self, other: &Image, tolerance: u8) -> Comparison {
+        assert!(self.width() == other.width() && self.height() == other.height(),
+            "Cannot compare a {}x{} image with a {}x{} one", self.width(), self.height(), other.width(), other.height());
+
+        let (pixels, others) = (self.pixels(), other.pixels());
+        let mut max_error = 0;
+        let mut total = 0f64;
+        let mut squared = 0f64;
+        let mut mismatched = 0;
+
+        let diff = pixels.iter().zip(others.iter()).map(|(a, b)| {
+            let errors = [a.red.abs_diff(b.red), a.green.abs_diff(b.green), a.blue.abs_diff(b.blue), a.alpha.abs_diff(b.alpha)];
+            let worst = errors.into_iter().max().unwrap_or(0);
+
+            max_error = max_error.max(worst);
+            total += errors.iter().map(|&e| e as f64).sum::<f64>();
+            squared += errors.iter().map(|&e| (e as f64).powi(2)).sum::<f64>();
+
+            if worst > tolerance {
+                mismatched += 1;
+                COLOR_RED
+            } else {
+                let gray = ((a.red as u16 + a.green as u16 + a.blue as u16) / 9) as u8;
+                new_color!(gray, gray, gray)
+            }
+        }).collect::<Vec<_>>();
+
+        let samples = (pixels.len() * 4).max(1) as f64;
+        let mse = squared / samples;
+
+        Comparison {
+            max_error,
+            mean_error: total / samples,
+            psnr: 10f64 * (255f64 * 255f64 / mse).log10(),
+            mismatched,
+            diff: Image::from_pixels(self.width(), self.height(), diff),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use rayon::prelude::*;
+
+use crate::image::Image;
+
+// rec. 709 weights for how bright each channel looks
+const LUMA: [f64; 3] = [0.2126, 0.7152, 0.0722];
+
+// a window this wide is already 16641 values per channel per pixel
+pub const MAX_MEDIAN_RADIUS: usize = 64;
+
+const SOBEL_X: [f64; 9] = [-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0];
+const SOBEL_Y: [f64; 9] = [1.0, 2.0, 1.0, 0.0, 0.0, 0.0, -1.0, -2.0, -1.0];
+
+// weights row by row from the top left, centered on the pixel being filtered
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f64>,
+}
+
+impl Kernel {
+    pub fn new(width: usize, height: usize, weights: Vec<f64>) -> Kernel {
+        assert!(width % 2 == 1 && height % 2 == 1, "Kernel sides must be odd so that it has a center");
+        assert_eq!(weights.len(), width * height, "Weight count does not match the kernel size");
+
+        Kernel { width, height, weights }
+    }
+
+    // sampled out to three standard deviations either side and normalized to keep the overall brightness
+    fn gaussian(sigma: f64) -> Vec<f64> {
+        let radius = (3f64 * sigma).ceil() as i64;
+        let weights = (-radius..=radius).map(|i| (-((i * i) as f64) / (2f64 * sigma * sigma)).exp()).collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+
+        weights.into_iter().map(|w| w / total).collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageFilter {
+    Convolve(Kernel),
+    // standard deviation in pixels
+    GaussianBlur(f64),
+    // pushes every pixel amount times further away from a gaussian blur of itself
+    UnsharpMask { sigma: f64, amount: f64 },
+    // how fast the brightness changes, in gray
+    Sobel,
+    // the middle value of every channel over a square reaching radius pixels out, at most MAX_MEDIAN_RADIUS
+    Median(usize),
+}
+
+impl Image {
+    // filters the finished pixels, in linear light for hdr images; pixels past the border repeat the edge
+    pub fn apply_filter(&mut self, filter: &ImageFilter) {
+        let (width, height) = (self.width(), self.height());
+        let pixels = self.filter_pixels();
+
+        let filtered = match filter {
+            ImageFilter::Convolve(kernel) => straight(&convolve(&premultiply(&pixels), width, height, kernel)),
+            ImageFilter::GaussianBlur(sigma) => straight(&blur(&premultiply(&pixels), width, height, *sigma)),
+            ImageFilter::UnsharpMask { sigma, amount } => {
+                let blurred = straight(&blur(&premultiply(&pixels), width, height, *sigma));
+
+                pixels.iter().zip(&blurred).map(|(p, b)| {
+                    let sharpen = |i: usize| (p[i] + amount * (p[i] - b[i])).max(0f64);
+                    [sharpen(0), sharpen(1), sharpen(2), p[3]]
+                }).collect()
+            },
+            ImageFilter::Sobel => sobel(&pixels, width, height),
+            ImageFilter::Median(radius) => median(&pixels, width, height, *radius),
+        };
+
+        self.set_filter_pixels(&filtered);
+    }
+}
+
+// colors weighted by alpha, so see-through pixels don't bleed their color into their neighbours
+fn premultiply(pixels: &[[f64; 4]]) -> Vec<[f64; 4]> {
+    pixels.iter().map(|&[r, g, b, a]| [r * a, g * a, b * a, a]).collect()
+}
+
+fn straight(pixels: &[[f64; 4]]) -> Vec<[f64; 4]> {
+    pixels.iter().map(|&[r, g, b, a]| if a > 0f64 { [r / a, g / a, b / a, a] } else { [0f64; 4] }).collect()
+}
+
+// the index of the pixel offset steps away from i along a side, held at the border
+fn clamp(i: usize, offset: isize, extent: usize) -> usize {
+    (i as isize + offset).clamp(0, extent as isize - 1) as usize
+}
+
+fn convolve(pixels: &[[f64; 4]], width: usize, height: usize, kernel: &Kernel) -> Vec<[f64; 4]> {
+    let (rx, ry) = ((kernel.width / 2) as isize, (kernel.height / 2) as isize);
+    let mut out = vec![[0f64; 4]; pixels.len()];
+
+    out.par_chunks_mut(width.max(1)).enumerate().for_each(|(y, row)| {
+        for (x, sum) in row.iter_mut().enumerate() {
+            for (k, &weight) in kernel.weights.iter().enumerate() {
+                let kx = (k % kernel.width) as isize - rx;
+                let ky = (k / kernel.width) as isize - ry;
+                let p = pixels[clamp(x, kx, width) + clamp(y, ky, height) * width];
+
+                for (s, c) in sum.iter_mut().zip(p) {
+                    *s += weight * c;
+                }
+            }
+        }
+    });
+
+    out
+}
+
+// one pass across and one down instead of the full square
+fn blur(pixels: &[[f64; 4]], width: usize, height: usize, sigma: f64) -> Vec<[f64; 4]> {
+    let weights = Kernel::gaussian(sigma);
+    let across = Kernel::new(weights.len(), 1, weights.clone());
+    let down = Kernel::new(1, weights.len(), weights);
+
+    convolve(&convolve(pixels, width, height, &across), width, height, &down)
+}
+
+fn sobel(pixels: &[[f64; 4]], width: usize, height: usize) -> Vec<[f64; 4]> {
+    let luma = pixels.iter().map(|p| {
+        let y = LUMA.iter().zip(p).map(|(w, c)| w * c).sum::<f64>();
+        [y, y, y, 1f64]
+    }).collect::<Vec<_>>();
+
+    let gx = convolve(&luma, width, height, &Kernel::new(3, 3, SOBEL_X.to_vec()));
+    let gy = convolve(&luma, width, height, &Kernel::new(3, 3, SOBEL_Y.to_vec()));
+
+    gx.iter().zip(&gy).zip(pixels).map(|((x, y), p)| {
+        let magnitude = x[0].hypot(y[0]);
+        [magnitude, magnitude, magnitude, p[3]]
+    }).collect()
+}
+
+fn median(pixels: &[[f64; 4]], width: usize, height: usize, radius: usize) -> Vec<[f64; 4]> {
+    assert!(radius <= MAX_MEDIAN_RADIUS, "Median radius {} is larger than {}", radius, MAX_MEDIAN_RADIUS);
+    let r = radius as isize;
+    let mut out = vec![[0f64; 4]; pixels.len()];
+
+    out.par_chunks_mut(width.max(1)).enumerate().for_each(|(y, row)| {
+        let mut window = Vec::with_capacity((2 * radius + 1).pow(2));
+
+        for (x, middle) in row.iter_mut().enumerate() {
+            for (c, value) in middle.iter_mut().enumerate() {
+                window.clear();
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        window.push(pixels[clamp(x, dx, width) + clamp(y, dy, height) * width][c]);
+                    }
+                }
+
+                let half = window.len() / 2;
+                *value = *window.select_nth_unstable_by(half, f64::total_cmp).1;
+            }
+        }
+    });
+
+    out
+}
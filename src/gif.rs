@@ -0,0 +1,245 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::write;
+use std::io;
+
+use crate::bits::BitWriter;
+use crate::color::Color;
+use crate::image::Image;
+use crate::new_color;
+
+const MAX_CODES: usize = 4096;
+
+pub struct GifEncoder {
+    // hundredths of a second between frames
+    pub delay: u16,
+    // 0 repeats forever
+    pub loops: u16,
+}
+
+impl Default for GifEncoder {
+    fn default() -> Self {
+        Self {
+            delay: 4,
+            loops: 0,
+        }
+    }
+}
+
+impl GifEncoder {
+    pub fn save(&self, frames: &[Image], filename: &str) -> io::Result<()> {
+        write(filename, self.encode(frames)?)
+    }
+
+    pub fn encode(&self, frames: &[Image]) -> io::Result<Vec<u8>> {
+        let first = frames.first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "a gif needs at least one frame"))?;
+        let (width, height) = (first.width(), first.height());
+
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "image is too large for a gif"));
+        }
+        if frames.iter().any(|frame| frame.width() != width || frame.height() != height) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "gif frames must all be the same size"));
+        }
+
+        let mut out = b"GIF89a".to_vec();
+
+        // logical screen descriptor without a global color table, every frame brings its own
+        out.extend_from_slice(&(width as u16).to_le_bytes());
+        out.extend_from_slice(&(height as u16).to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0]);
+
+        if frames.len() > 1 {
+            out.extend_from_slice(&[0x21, 0xff, 0x0b]);
+            out.extend_from_slice(b"NETSCAPE2.0");
+            out.extend_from_slice(&[0x03, 0x01]);
+            out.extend_from_slice(&self.loops.to_le_bytes());
+            out.push(0);
+        }
+
+        for frame in frames {
+            let (palette, indices) = quantize(&frame.pixels());
+            let bits = palette_bits(palette.len());
+
+            // graphic control extension, each frame replaces the previous one
+            out.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+            out.extend_from_slice(&self.delay.to_le_bytes());
+            out.extend_from_slice(&[0, 0]);
+
+            // image descriptor with a local color table of 2^bits entries
+            out.push(0x2c);
+            out.extend_from_slice(&[0, 0, 0, 0]);
+            out.extend_from_slice(&(width as u16).to_le_bytes());
+            out.extend_from_slice(&(height as u16).to_le_bytes());
+            out.push(0x80 | (bits - 1));
+
+            for i in 0..1 << bits {
+                let color = palette.get(i).copied().unwrap_or(new_color!(0, 0, 0));
+                out.extend_from_slice(&[color.red, color.green, color.blue]);
+            }
+
+            let min_code_size = bits.max(2);
+            out.push(min_code_size);
+            for block in lzw(&indices, min_code_size).chunks(255) {
+                out.push(block.len() as u8);
+                out.extend_from_slice(block);
+            }
+            out.push(0);
+        }
+
+        out.push(0x3b);
+
+        Ok(out)
+    }
+}
+
+fn palette_bits(len: usize) -> u8 {
+    let mut bits = 1;
+    while 1 << bits < len {
+        bits += 1;
+    }
+
+    bits
+}
+
+fn pack(color: Color) -> u32 {
+    (color.red as u32) << 16 | (color.green as u32) << 8 | color.blue as u32
+}
+
+fn unpack(color: u32) -> [u8; 3] {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+// median cut down to at most 256 colors, then every pixel takes its nearest palette entry
+fn quantize(pixels: &[Color]) -> (Vec<Color>, Vec<u8>) {
+    // ordered so that ties in the median cut always split the same way
+    let mut histogram = BTreeMap::<u32, usize>::new();
+    for &color in pixels {
+        *histogram.entry(pack(color)).or_insert(0) += 1;
+    }
+
+    let mut boxes = vec![histogram.into_iter().collect::<Vec<_>>()];
+    while boxes.len() < 256 {
+        let widest = boxes.iter().enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range);
+
+        let (i, channel, _) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_by_key(|&(color, _)| unpack(color)[channel]);
+
+        // split where half the pixels fall on each side, keeping both halves non-empty
+        let total = colors.iter().map(|&(_, count)| count).sum::<usize>();
+        let mut seen = 0;
+        let mut split = 1;
+        for (j, &(_, count)) in colors.iter().enumerate() {
+            seen += count;
+            if seen * 2 >= total {
+                split = (j + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let palette = boxes.iter().map(|colors| {
+        let total = colors.iter().map(|&(_, count)| count).sum::<usize>().max(1);
+        let mut sum = [0usize; 3];
+        for &(color, count) in colors {
+            for (s, c) in sum.iter_mut().zip(unpack(color)) {
+                *s += c as usize * count;
+            }
+        }
+        let [r, g, b] = sum.map(|s| ((s + total / 2) / total) as u8);
+
+        new_color!(r, g, b)
+    }).collect::<Vec<_>>();
+
+    let mut nearest = HashMap::<u32, u8>::new();
+    let indices = pixels.iter().map(|&color| {
+        *nearest.entry(pack(color)).or_insert_with(|| {
+            palette.iter().enumerate()
+                .min_by_key(|(_, p)| distance(color, **p))
+                .map(|(i, _)| i as u8)
+                .unwrap_or(0)
+        })
+    }).collect();
+
+    (palette, indices)
+}
+
+fn widest_channel(colors: &[(u32, usize)]) -> (usize, u8) {
+    (0..3).map(|channel| {
+        let values = colors.iter().map(|&(color, _)| unpack(color)[channel]);
+        let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+        (channel, range)
+    }).max_by_key(|&(_, range)| range).unwrap()
+}
+
+fn distance(a: Color, b: Color) -> u32 {
+    let dr = a.red as i32 - b.red as i32;
+    let dg = a.green as i32 - b.green as i32;
+    let db = a.blue as i32 - b.blue as i32;
+
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+// variable width codes packed least significant bit first, starting over with a clear code once the table fills up
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut writer = BitWriter::default();
+    let mut table = HashMap::<(u16, u8), u16>::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+
+    writer.write(clear, size);
+
+    let mut prefix = match indices.first() {
+        Some(&first) => first as u16,
+        None => {
+            writer.write(end, size);
+            return writer.finish();
+        },
+    };
+
+    for &index in &indices[1..] {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix, size);
+
+        if (next as usize) < MAX_CODES {
+            table.insert((prefix, index), next);
+            if next == 1 << size && size < 12 {
+                size += 1;
+            }
+            next += 1;
+        } else {
+            writer.write(clear, size);
+            table.clear();
+            next = end + 1;
+            size = min_code_size + 1;
+        }
+
+        prefix = index as u16;
+    }
+
+    writer.write(prefix, size);
+    writer.write(end, size);
+
+    writer.finish()
+}
@@ -0,0 +1,51 @@
+use crate::color::Color;
+
+// squeezes unbounded linear light into 0 to 1 before gamma
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ToneMap {
+    // c / (1 + c), gentle but never quite reaches white
+    #[default]
+    Reinhard,
+    // narkowicz's fit of the aces filmic curve, more contrast and a proper shoulder
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(self, c: f64) -> f64 {
+        let c = c.max(0f64);
+        match self {
+            ToneMap::Reinhard => c / (1f64 + c),
+            ToneMap::Aces => (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0f64, 1f64),
+        }
+    }
+
+    // linear red, green and blue with alpha between 0 and 1 out to a displayable color
+    pub fn to_color(self, linear: [f32; 4]) -> Color {
+        let [r, g, b, a] = linear.map(|c| c as f64);
+        let encode = |c: f64| srgb_encode(self.apply(c)) * 255f64;
+
+        Color::from_channels([encode(r), encode(g), encode(b), a * 255f64])
+    }
+}
+
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1f64 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_decode(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// a plain color into linear light, alpha passes through untouched
+pub fn linear(color: Color) -> [f32; 4] {
+    let [r, g, b, a] = color.channels().map(|c| c / 255f64);
+    [srgb_decode(r), srgb_decode(g), srgb_decode(b), a].map(|c| c as f32)
+}
@@ -1,51 +1,498 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::fs::{read, write};
+use std::io;
+use std::path::Path;
 
+use crate::camera::Camera;
+use crate::hdr::{self, ToneMap};
 use crate::color::*;
-use crate::math::DynMatrix;
+use crate::lighting::{Constants, Lighting};
+use crate::math::{ConstMatrix, DynMatrix, Vector, vector};
+use crate::png;
+use crate::ppm::{self, PpmFormat};
+use crate::stroke::{LineCap, LineJoin, LineStyle, MITER_LIMIT};
 
+const VIEW: Vector = [0f64, 0f64, 1f64];
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shading {
+    Flat,
+    Gouraud,
+    Phong,
+}
+
+// how the samples under each pixel are weighted when supersampling
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    // an even average of the samples inside the pixel
+    #[default]
+    Box,
+    // falls off linearly and reaches halfway into the neighbouring pixels
+    Tent,
+}
+
+#[derive(Clone)]
 pub struct Image {
+    // every buffer holds samples rather than pixels, samples times as many of them along each side
     height: usize,
     width: usize,
+    samples: usize,
+    filter: Filter,
     raster: Vec<Color>,
+    zbuffer: Vec<f64>,
+    culling: bool,
+    shading: Shading,
+    blend_mode: BlendMode,
+    camera: Option<Camera>,
+    // linear light behind the raster, which then only holds its tone mapped copy
+    hdr: Option<Vec<[f32; 4]>>,
+    tone_map: ToneMap,
 }
 
 impl Image {
     pub fn new(width: usize, height: usize, color: Color) -> Image {
         let raster = vec![color; width * height];
+        let zbuffer = vec![f64::NEG_INFINITY; width * height];
         Image {
             width,
             height,
+            samples: 1,
+            filter: Filter::default(),
             raster,
+            zbuffer,
+            culling: true,
+            shading: Shading::Flat,
+            blend_mode: BlendMode::Normal,
+            camera: None,
+            hdr: None,
+            tone_map: ToneMap::default(),
+        }
+    }
+
+    pub fn new_hdr(width: usize, height: usize, color: Color, tone_map: ToneMap) -> Image {
+        let mut image = Image::new(width, height, color);
+        image.set_hdr(Some(tone_map));
+        image
+    }
+
+    // pixels run row by row from the top left corner
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Image {
+        assert_eq!(pixels.len(), width * height, "Pixel count does not match the image size");
+
+        let mut image = Image::new(width, height, COLOR_BLACK);
+        image.raster = pixels;
+        image
+    }
+
+    pub fn open(filename: &str) -> io::Result<Image> {
+        ppm::decode(&read(filename)?)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width / self.samples
+    }
+
+    pub fn height(&self) -> usize {
+        self.height / self.samples
+    }
+
+    // row by row from the top left corner, already tone mapped for hdr images and filtered down when supersampling
+    pub fn pixels(&self) -> Cow<'_, [Color]> {
+        if self.samples == 1 {
+            return Cow::Borrowed(&self.raster);
+        }
+
+        Cow::Owned(match &self.hdr {
+            Some(hdr) => self.downsample(|i| hdr[i].map(|c| c as f64))
+                .into_iter()
+                .map(|c| self.tone_map.to_color(c.map(|c| c as f32)))
+                .collect(),
+            None => self.downsample(|i| self.raster[i].channels().map(|c| c / 255f64))
+                .into_iter()
+                .map(|c| Color::from_channels(c.map(|c| c * 255f64)))
+                .collect(),
+        })
+    }
+
+    // linear red, green, blue and alpha in the same order as pixels, for hdr images only
+    pub fn linear_pixels(&self) -> Option<Cow<'_, [[f32; 4]]>> {
+        let hdr = self.hdr.as_ref()?;
+        if self.samples == 1 {
+            return Some(Cow::Borrowed(hdr));
+        }
+
+        Some(Cow::Owned(self.downsample(|i| hdr[i].map(|c| c as f64)).into_iter().map(|c| c.map(|c| c as f32)).collect()))
+    }
+
+    // the format follows the extension, anything other than .png is written as a binary ppm
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let extension = Path::new(filename).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("png") => write(filename, png::encode(self)),
+            _ => self.save_ppm(filename, PpmFormat::default()),
+        }
+    }
+
+    pub fn save_ppm(&self, filename: &str, format: PpmFormat) -> io::Result<()> {
+        write(filename, ppm::encode(self, format))
+    }
+
+    // open surfaces need culling turned off so their insides stay visible
+    pub fn set_culling(&mut self, culling: bool) {
+        self.culling = culling;
+    }
+
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.shading = shading;
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    // switching on keeps what is already drawn, switching off keeps only its tone mapped copy
+    pub fn set_hdr(&mut self, tone_map: Option<ToneMap>) {
+        match tone_map {
+            Some(tone_map) => {
+                let hdr = self.hdr.get_or_insert_with(|| self.raster.iter().map(|&c| hdr::linear(c)).collect());
+                self.tone_map = tone_map;
+                self.raster = hdr.iter().map(|&c| tone_map.to_color(c)).collect();
+            },
+            None => self.hdr = None,
         }
     }
 
+    // renders samples by samples times over for every pixel, keeping what is already drawn but forgetting its depth
+    pub fn set_supersampling(&mut self, samples: usize, filter: Filter) {
+        assert!(samples > 0, "Supersampling needs at least one sample per pixel");
+
+        let (width, height) = (self.width(), self.height());
+        let pixels = self.pixels().into_owned();
+        let linear = self.linear_pixels().map(Cow::into_owned);
+
+        self.samples = samples;
+        self.filter = filter;
+        self.width = width * samples;
+        self.height = height * samples;
+        self.raster = (0..self.width * self.height).map(|i| pixels[self.pixel_of(i)]).collect();
+        self.zbuffer = vec![f64::NEG_INFINITY; self.width * self.height];
+        self.hdr = linear.map(|linear| (0..self.width * self.height).map(|i| linear[self.pixel_of(i)]).collect());
+    }
+
+    // the finished pixels with channels between 0 and 1, or as linear light for hdr images
+    pub(crate) fn filter_pixels(&self) -> Vec<[f64; 4]> {
+        match self.linear_pixels() {
+            Some(linear) => linear.iter().map(|c| c.map(|c| c as f64)).collect(),
+            None => self.pixels().iter().map(|c| c.channels().map(|c| c / 255f64)).collect(),
+        }
+    }
+
+    // puts filtered pixels back in place of everything drawn so far, leaving depth alone
+    pub(crate) fn set_filter_pixels(&mut self, pixels: &[[f64; 4]]) {
+        let samples = 0..self.width * self.height;
+
+        match &self.hdr {
+            Some(_) => {
+                let hdr = samples.map(|i| pixels[self.pixel_of(i)].map(|c| c as f32)).collect::<Vec<_>>();
+                self.raster = hdr.iter().map(|&c| self.tone_map.to_color(c)).collect();
+                self.hdr = Some(hdr);
+            },
+            None => {
+                self.raster = samples.map(|i| Color::from_channels(pixels[self.pixel_of(i)].map(|c| c * 255f64))).collect();
+            },
+        }
+    }
+
+    // without a camera, points are already in screen coordinates and viewed orthographically down -z
+    pub fn set_camera(&mut self, camera: Option<Camera>) {
+        self.camera = camera;
+    }
+
     pub fn reset(&mut self, color: Color) {
         self.raster = vec![color; self.width * self.height];
+        self.zbuffer = vec![f64::NEG_INFINITY; self.width * self.height];
+
+        if let Some(hdr) = &mut self.hdr {
+            let linear = hdr::linear(color);
+            *hdr = vec![linear; self.width * self.height];
+            self.raster = vec![self.tone_map.to_color(linear); self.width * self.height];
+        }
+    }
+
+    // drops the old contents along with the z-buffer
+    pub fn resize(&mut self, width: usize, height: usize, color: Color) {
+        self.width = width * self.samples;
+        self.height = height * self.samples;
+        self.reset(color);
     }
 
-    pub fn draw_lines(&mut self, m: &DynMatrix<f64>, color: Color) {
+    pub fn draw_lines(&mut self, m: &DynMatrix<f64>, color: Color, style: &LineStyle) {
+        // widths and dashes are measured in pixels of the finished image
+        let scaled;
+        let style = match self.samples {
+            1 => style,
+            n => {
+                scaled = LineStyle {
+                    width: style.width * n as f64,
+                    dash: style.dash.iter().map(|length| length * n as f64).collect(),
+                    ..style.clone()
+                };
+                &scaled
+            },
+        };
+
+        let projection = self.projection();
+        // edges that start where the previous one ended get joined to it and carry its dash pattern on
+        let mut previous: Option<(Vector, Vector)> = None;
+        let mut phase = 0f64;
+
         for chunk in m.matrix.chunks(8) {
-            self.draw_line(chunk[0] as i32, chunk[1] as i32, chunk[4] as i32, chunk[5] as i32, color);
+            let edge = match &projection {
+                Some(projection) => self.project_edge(projection, &chunk[0..4], &chunk[4..8]),
+                None => Some((self.to_samples([chunk[0], chunk[1], chunk[2]]), self.to_samples([chunk[4], chunk[5], chunk[6]]))),
+            };
+
+            let (p0, p1) = match edge {
+                Some(edge) => edge,
+                None => {
+                    previous = None;
+                    continue;
+                },
+            };
+
+            match previous {
+                Some((start, end)) if end == p0 => {
+                    if style.is_wide() && style.is_drawn(phase) {
+                        self.draw_join(start, p0, p1, color, style);
+                    }
+                },
+                _ => phase = 0f64,
+            }
+
+            self.draw_edge(p0, p1, phase, color, style);
+            phase += planar_length(p0, p1);
+            previous = Some((p0, p1));
+        }
+    }
+
+    pub fn draw_polygons(&mut self, m: &DynMatrix<f64>, lighting: &Lighting, constants: &Constants) {
+        let projection = self.projection();
+        let eye = self.camera.map(|camera| camera.eye);
+        let linear = self.hdr.is_some();
+        let normals = match self.shading {
+            Shading::Flat => Vec::new(),
+            Shading::Gouraud | Shading::Phong => m.vertex_normals(),
+        };
+
+        for (i, chunk) in m.matrix.chunks(12).enumerate() {
+            let p0 = [chunk[0], chunk[1], chunk[2]];
+            let p1 = [chunk[4], chunk[5], chunk[6]];
+            let p2 = [chunk[8], chunk[9], chunk[10]];
+
+            // triangles reaching behind the near plane are dropped rather than clipped
+            let screen = match &projection {
+                Some(projection) => match [p0, p1, p2].map(|p| self.project(projection, &p)) {
+                    [Some(s0), Some(s1), Some(s2)] => [s0, s1, s2],
+                    _ => continue,
+                },
+                None => [p0, p1, p2].map(|p| self.to_samples(p)),
+            };
+
+            if self.culling && vector::surface_normal(screen[0], screen[1], screen[2])[2] <= 0f64 {
+                continue;
+            }
+
+            match self.shading {
+                Shading::Flat => {
+                    let normal = vector::surface_normal(p0, p1, p2);
+                    let centroid = vector::scale(vector::add(vector::add(p0, p1), p2), 1f64 / 3f64);
+                    let light = lighting.intensity(centroid, normal, view(eye, centroid), constants, linear);
+
+                    self.scanline_convert(screen.map(|s| Vertex { position: s, attributes: [] }), |_| light);
+                },
+                Shading::Gouraud => {
+                    let vertices = [(p0, 0), (p1, 1), (p2, 2)].map(|(p, j)| {
+                        let light = lighting.intensity(p, normals[i * 3 + j], view(eye, p), constants, linear);
+                        Vertex { position: screen[j], attributes: light }
+                    });
+
+                    self.scanline_convert(vertices, |light| *light);
+                },
+                Shading::Phong => {
+                    let vertices = [(p0, 0), (p1, 1), (p2, 2)].map(|(p, j)| {
+                        let n = normals[i * 3 + j];
+                        Vertex { position: screen[j], attributes: [n[0], n[1], n[2], p[0], p[1], p[2]] }
+                    });
+
+                    self.scanline_convert(vertices, |a| {
+                        let p = [a[3], a[4], a[5]];
+                        lighting.intensity(p, [a[0], a[1], a[2]], view(eye, p), constants, linear)
+                    });
+                },
+            }
+        }
+    }
+
+    fn projection(&self) -> Option<ConstMatrix<f64, 4, 4>> {
+        self.camera.map(|camera| camera.matrix(self.width as f64 / self.height as f64))
+    }
+
+    // the pixel, counted from the top left corner, whose block of samples holds the sample at index
+    fn pixel_of(&self, index: usize) -> usize {
+        let (x, y) = (index % self.width / self.samples, index / self.width / self.samples);
+        x + y * self.width()
+    }
+
+    // pixel coordinates to sample coordinates, depth stays as it is
+    fn to_samples(&self, p: Vector) -> Vector {
+        let n = self.samples as f64;
+        [p[0] * n, p[1] * n, p[2]]
+    }
+
+    // weighted averages of the samples under every pixel, from the top left corner; colors count by their alpha
+    // so that see-through samples don't darken the edges they share with opaque ones
+    fn downsample(&self, sample: impl Fn(usize) -> [f64; 4]) -> Vec<[f64; 4]> {
+        let (width, height) = (self.width(), self.height());
+        let columns = (0..width).map(|x| self.taps(x, self.width)).collect::<Vec<_>>();
+        let rows = (0..height).map(|y| self.taps(y, self.height)).collect::<Vec<_>>();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in &rows {
+            for column in &columns {
+                let mut sum = [0f64; 4];
+                let mut total = 0f64;
+
+                for &(y, wy) in row {
+                    for &(x, wx) in column {
+                        let [r, g, b, a] = sample(x + y * self.width);
+                        let weight = wx * wy;
+                        for (s, v) in sum.iter_mut().zip([r * a, g * a, b * a, a]) {
+                            *s += v * weight;
+                        }
+                        total += weight;
+                    }
+                }
+
+                let [r, g, b, a] = sum.map(|s| s / total);
+                pixels.push(if a > 0f64 { [r / a, g / a, b / a, a] } else { [0f64; 4] });
+            }
+        }
+
+        pixels
+    }
+
+    // the samples along one side that feed the pixel at index, with their weights
+    fn taps(&self, index: usize, extent: usize) -> Vec<(usize, f64)> {
+        let n = self.samples;
+        match self.filter {
+            Filter::Box => (index * n..(index + 1) * n).map(|s| (s, 1f64)).collect(),
+            Filter::Tent => {
+                let center = (index as f64 + 0.5) * n as f64;
+                ((index * n).saturating_sub(n / 2 + 1)..((index + 1) * n + n / 2 + 1).min(extent))
+                    .map(|s| (s, 1f64 - ((s as f64 + 0.5 - center) / n as f64).abs()))
+                    .filter(|&(_, weight)| weight > 0f64)
+                    .collect()
+            },
+        }
+    }
+
+    // clip space to screen space; depth is flipped so that larger z stays closer to the viewer
+    fn to_screen(&self, clip: [f64; 4]) -> Vector {
+        [
+            (clip[0] / clip[3] + 1f64) * self.width as f64 / 2f64,
+            (clip[1] / clip[3] + 1f64) * self.height as f64 / 2f64,
+            -clip[2] / clip[3],
+        ]
+    }
+
+    fn project(&self, projection: &ConstMatrix<f64, 4, 4>, p: &[f64]) -> Option<Vector> {
+        let clip = projection.transform([p[0], p[1], p[2], 1f64]);
+
+        if near_distance(clip) < 0f64 {
+            None
+        } else {
+            Some(self.to_screen(clip))
+        }
+    }
+
+    // cuts the edge off at the near plane before dividing so nothing behind the eye gets flipped onto the screen
+    fn project_edge(&self, projection: &ConstMatrix<f64, 4, 4>, p0: &[f64], p1: &[f64]) -> Option<(Vector, Vector)> {
+        let mut c0 = projection.transform([p0[0], p0[1], p0[2], 1f64]);
+        let mut c1 = projection.transform([p1[0], p1[1], p1[2], 1f64]);
+        let d0 = near_distance(c0);
+        let d1 = near_distance(c1);
+
+        if d0 < 0f64 && d1 < 0f64 {
+            return None;
+        }
+
+        if d0 < 0f64 || d1 < 0f64 {
+            let t = d0 / (d0 - d1);
+            let cut = [0, 1, 2, 3].map(|i| c0[i] + (c1[i] - c0[i]) * t);
+
+            if d0 < 0f64 {
+                c0 = cut;
+            } else {
+                c1 = cut;
+            }
+        }
+
+        Some((self.to_screen(c0), self.to_screen(c1)))
+    }
+
+    // fills the rows and columns whose pixel centers fall inside the triangle, so neighbouring triangles never overlap
+    fn scanline_convert<const N: usize>(&mut self, vertices: [Vertex<N>; 3], shade: impl Fn(&[f64; N]) -> [f64; 4]) {
+        let mut vertices = vertices;
+        vertices.sort_by(|a, b| a.position[1].total_cmp(&b.position[1]));
+        let [bot, mid, top] = vertices;
+
+        let y_start = bot.position[1].ceil().max(0f64) as i32;
+        let y_end = top.position[1].ceil().min(self.height as f64) as i32;
+
+        for y in y_start..y_end {
+            let yf = y as f64;
+
+            let mut v0 = bot.lerp(&top, (yf - bot.position[1]) / (top.position[1] - bot.position[1]));
+            let mut v1 = if yf < mid.position[1] {
+                bot.lerp(&mid, (yf - bot.position[1]) / (mid.position[1] - bot.position[1]))
+            } else {
+                mid.lerp(&top, (yf - mid.position[1]) / (top.position[1] - mid.position[1]))
+            };
+
+            if v0.position[0] > v1.position[0] {
+                std::mem::swap(&mut v0, &mut v1);
+            }
+
+            let x_start = v0.position[0].ceil().max(0f64) as i32;
+            let x_end = v1.position[0].ceil().min(self.width as f64) as i32;
+
+            for x in x_start..x_end {
+                let v = v0.lerp(&v1, (x as f64 - v0.position[0]) / (v1.position[0] - v0.position[0]));
+                self.plot_light(x, y, v.position[2], shade(&v.attributes));
+            }
         }
     }
 
-    fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line(&mut self, x0: i32, y0: i32, z0: f64, x1: i32, y1: i32, z1: f64, color: Color) {
         if x0 > x1 {
-            self.draw_line_help(x1, y1, x0, y0, color);
+            self.draw_line_help(x1, y1, z1, x0, y0, z0, color);
         } else {
-            self.draw_line_help(x0, y0, x1, y1, color);
+            self.draw_line_help(x0, y0, z0, x1, y1, z1, color);
         }
     }
 
-    fn draw_line_help(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line_help(&mut self, x0: i32, y0: i32, z0: f64, x1: i32, y1: i32, z1: f64, color: Color) {
         let slope = slope(x0, y0, x1, y1);
         let quad = match slope {
             Some(m) => if m > 1_f32 {
                     2
-                } else if 0_f32 <= m && m <= 1_f32 {
+                } else if (0_f32..=1_f32).contains(&m) {
                     1
-                } else if 0_f32 > m && m >= -1_f32 {
+                } else if (-1_f32..0_f32).contains(&m) {
                     4
                 } else {
                     3
@@ -55,26 +502,40 @@ impl Image {
 
         let mut x = x0;
         let mut y = y0;
+        let mut z = z0;
+
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        let dz = (z1 - z0) / steps as f64;
 
         let dx = 2 * (y1 - y0);
         let dy = 2 * (x0 - x1);
-        let mut d = 2 * dx + dy;
+        // the line function at the first midpoint, which sits on a different side of the start for every octant
+        let mut d = match quad {
+            2 => dx + 2 * dy,
+            3 => dx - 2 * dy,
+            4 => 2 * dx - dy,
+            _ => 2 * dx + dy,
+        };
 
         match quad {
             0 => if y0 > y1 {
                 y = y1;
+                z = z1;
                 while y <= y0 {
-                    self.plot(x, y, color);
+                    self.plot(x, y, z, color);
                     y += 1;
+                    z -= dz;
                 }
             } else {
                 while y <= y1 {
-                    self.plot(x, y, color);
+                    self.plot(x, y, z, color);
                     y += 1;
+                    z += dz;
                 }
             },
             1 => while x <= x1 {
-                self.plot(x, y, color);
+                self.plot(x, y, z, color);
+                z += dz;
 
                 if d > 0 {
                     y += 1;
@@ -85,7 +546,8 @@ impl Image {
                 d += 2 * dx;
             },
             2 => while y <= y1 {
-                self.plot(x, y, color);
+                self.plot(x, y, z, color);
+                z += dz;
 
                 if d < 0 {
                     x += 1;
@@ -96,7 +558,8 @@ impl Image {
                 d += 2 * dy;
             },
             3 => while y >= y1 {
-                self.plot(x, y, color);
+                self.plot(x, y, z, color);
+                z += dz;
 
                 if d > 0 {
                     x += 1;
@@ -106,8 +569,9 @@ impl Image {
                 y -= 1;
                 d -= 2 * dy;
             },
-            4 => while x <= x1 && y >= 0 {
-                self.plot(x, y, color);
+            4 => while x <= x1 {
+                self.plot(x, y, z, color);
+                z += dz;
 
                 if d < 0 {
                     y -= 1;
@@ -121,30 +585,267 @@ impl Image {
         }
     }
 
-    fn plot(&mut self, x: i32, y: i32, color: Color) {
-        self[(x as usize, y as usize)] = color;
+    // splits the edge into its dashes, leaving out whatever cannot reach the canvas
+    fn draw_edge(&mut self, p0: Vector, p1: Vector, phase: f64, color: Color, style: &LineStyle) {
+        // lines cover up to the far edge of the canvas and wide ones reach in from outside
+        let reach = if style.is_wide() { style.width } else { 0f64 };
+        let min = [-reach; 2];
+        let max = [self.width as f64 + reach, self.height as f64 + reach];
+
+        let (t0, t1) = match clip_line(p0, p1, min, max) {
+            Some(range) => range,
+            None => return,
+        };
+
+        let length = planar_length(p0, p1);
+        let at = |distance: f64| {
+            let t = if length == 0f64 { 0f64 } else { distance / length };
+            vector::add(p0, vector::scale(vector::subtract(p1, p0), t))
+        };
+
+        for (start, end) in style.dashes(phase, t0 * length, t1 * length) {
+            let (a, b) = (at(start), at(end));
+
+            if style.is_wide() {
+                self.draw_wide_line(a, b, color, style);
+            } else if style.antialias {
+                self.draw_line_antialiased(a, b, color);
+            } else {
+                // a point clipped onto the far edge truncates to one past the last pixel
+                let (w, h) = (self.width as i32 - 1, self.height as i32 - 1);
+                self.draw_line((a[0] as i32).min(w), (a[1] as i32).min(h), a[2], (b[0] as i32).min(w), (b[1] as i32).min(h), b[2], color);
+            }
+        }
+    }
+
+    // a band half the width to either side of the line, finished off with the caps
+    fn draw_wide_line(&mut self, a: Vector, b: Vector, color: Color, style: &LineStyle) {
+        let radius = style.width / 2f64;
+
+        match (planar_direction(a, b), style.cap) {
+            (Some(d), cap) => {
+                let n = [-d[1] * radius, d[0] * radius, 0f64];
+                let (a, b) = match cap {
+                    LineCap::Square => (vector::subtract(a, vector::scale(d, radius)), vector::add(b, vector::scale(d, radius))),
+                    _ => (a, b),
+                };
+
+                self.fill_polygon(&[vector::add(a, n), vector::add(b, n), vector::subtract(b, n), vector::subtract(a, n)], color);
+            },
+            // a zero length dash still shows up as a dot with square or round caps
+            (None, LineCap::Square) => {
+                let corners = [[-1f64, -1f64], [1f64, -1f64], [1f64, 1f64], [-1f64, 1f64]];
+                self.fill_polygon(&corners.map(|[x, y]| [a[0] + x * radius, a[1] + y * radius, a[2]]), color);
+            },
+            (None, _) => (),
+        }
+
+        if style.cap == LineCap::Round {
+            self.fill_disc(a, radius, color);
+            self.fill_disc(b, radius, color);
+        }
+    }
+
+    // fills the wedge left open on the outside of the bend where the edge from q to p meets the one from p to r
+    fn draw_join(&mut self, q: Vector, p: Vector, r: Vector, color: Color, style: &LineStyle) {
+        let radius = style.width / 2f64;
+
+        if style.join == LineJoin::Round {
+            self.fill_disc(p, radius, color);
+            return;
+        }
+
+        let (d0, d1) = match (planar_direction(q, p), planar_direction(p, r)) {
+            (Some(d0), Some(d1)) => (d0, d1),
+            _ => return,
+        };
+
+        // straight on or doubling back, there is no outside to fill
+        let turn = d0[0] * d1[1] - d0[1] * d1[0];
+        if turn == 0f64 {
+            return;
+        }
+
+        // the outside is on the right of a left turn and the left of a right turn
+        let side = -turn.signum() * radius;
+        let n0 = [-d0[1] * side, d0[0] * side, 0f64];
+        let n1 = [-d1[1] * side, d1[0] * side, 0f64];
+
+        if style.join == LineJoin::Miter {
+            let bisector = vector::normalize(vector::add(n0, n1));
+            let length = radius * radius / vector::dot(bisector, n0);
+
+            if length <= MITER_LIMIT * radius {
+                self.fill_polygon(&[p, vector::add(p, n0), vector::add(p, vector::scale(bisector, length)), vector::add(p, n1)], color);
+                return;
+            }
+        }
+
+        self.fill_polygon(&[p, vector::add(p, n0), vector::add(p, n1)], color);
+    }
+
+    // convex polygons only, fanned out from the first point
+    fn fill_polygon(&mut self, points: &[Vector], color: Color) {
+        let light = self.light(color);
+        for i in 1..points.len().saturating_sub(1) {
+            let vertices = [points[0], points[i], points[i + 1]].map(|position| Vertex { position, attributes: [] });
+            self.scanline_convert(vertices, |_| light);
+        }
+    }
+
+    // samples whole pixel coordinates like scanline_convert, at the depth of the center
+    fn fill_disc(&mut self, center: Vector, radius: f64, color: Color) {
+        let y_start = (center[1] - radius).ceil().max(0f64) as i32;
+        let y_end = (center[1] + radius).floor().min(self.height as f64 - 1f64) as i32;
+        let x_start = (center[0] - radius).ceil().max(0f64) as i32;
+        let x_end = (center[0] + radius).floor().min(self.width as f64 - 1f64) as i32;
+
+        for y in y_start..=y_end {
+            for x in x_start..=x_end {
+                let (dx, dy) = (x as f64 - center[0], y as f64 - center[1]);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.plot(x, y, center[2], color);
+                }
+            }
+        }
+    }
+
+    // xiaolin wu's line, shifted half a pixel so that whole coordinates land on pixel centers
+    fn draw_line_antialiased(&mut self, p0: Vector, p1: Vector, color: Color) {
+        let (mut x0, mut y0, mut z0) = (p0[0] - 0.5, p0[1] - 0.5, p0[2]);
+        let (mut x1, mut y1, mut z1) = (p1[0] - 0.5, p1[1] - 0.5, p1[2]);
+
+        // walk along the longer axis, left to right, swapping x and y back when plotting
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+            std::mem::swap(&mut z0, &mut z1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0f64 { 1f64 } else { (y1 - y0) / dx };
+        let depth = |x: f64| if dx == 0f64 { z0 } else { z0 + (z1 - z0) * (x - x0) / dx };
+
+        let plot = |image: &mut Self, x: f64, y: f64, coverage: f64| {
+            let (px, py) = if steep { (y, x) } else { (x, y) };
+            image.blend(px as i32, py as i32, depth(x), color, coverage);
+        };
+
+        // the end pixels only get the part of their column that the line actually covers
+        let x_start = x0.round();
+        let y_start = y0 + gradient * (x_start - x0);
+        let gap = 1f64 - fract(x0 + 0.5);
+        plot(self, x_start, y_start.floor(), (1f64 - fract(y_start)) * gap);
+        plot(self, x_start, y_start.floor() + 1f64, fract(y_start) * gap);
+
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let gap = fract(x1 + 0.5);
+        plot(self, x_end, y_end.floor(), (1f64 - fract(y_end)) * gap);
+        plot(self, x_end, y_end.floor() + 1f64, fract(y_end) * gap);
+
+        let mut y = y_start + gradient;
+        let mut x = x_start + 1f64;
+        while x < x_end {
+            plot(self, x, y.floor(), 1f64 - fract(y));
+            plot(self, x, y.floor() + 1f64, fract(y));
+            y += gradient;
+            x += 1f64;
+        }
+    }
+
+    // lays a partially covered pixel over what is already there; only mostly covered pixels claim the z-buffer
+    fn blend(&mut self, x: i32, y: i32, z: f64, color: Color, coverage: f64) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height || coverage <= 0f64 {
+            return;
+        }
+
+        let idx = self.offset(x as usize, y as usize);
+        let mut light = self.light(color);
+        light[3] *= coverage.min(1f64);
+        self.fragment(idx, z, light, color.alpha == 255 && coverage >= 0.5);
+    }
+
+    fn plot(&mut self, x: i32, y: i32, z: f64, color: Color) {
+        self.plot_light(x, y, z, self.light(color));
+    }
+
+    fn plot_light(&mut self, x: i32, y: i32, z: f64, light: [f64; 4]) {
+        let idx = self.offset(x as usize, y as usize);
+        self.fragment(idx, z, light, light[3] >= 255f64);
+    }
+
+    // a plain color as light on the 0 to 255 scale that lighting works in, which is linear for hdr images
+    fn light(&self, color: Color) -> [f64; 4] {
+        match self.hdr {
+            Some(_) => hdr::linear(color).map(|c| c as f64 * 255f64),
+            None => color.channels(),
+        }
+    }
+
+    // translucent colors never hide anything from the z-buffer, so they belong after everything they cover
+    fn fragment(&mut self, idx: usize, z: f64, light: [f64; 4], opaque: bool) {
+        if z <= self.zbuffer[idx] {
+            return;
+        }
+
+        match &mut self.hdr {
+            // light is only clamped once it lands in a byte
+            None => {
+                let color = Color::from_channels(light);
+                self.raster[idx] = match (color.alpha, self.blend_mode) {
+                    (255, BlendMode::Normal) => color,
+                    _ => color.over(self.raster[idx], self.blend_mode),
+                };
+            },
+            Some(hdr) => {
+                let src = light.map(|c| c / 255f64);
+                let linear = match (src[3] >= 1f64, self.blend_mode) {
+                    (true, BlendMode::Normal) => src,
+                    _ => composite(src, hdr[idx].map(|c| c as f64), self.blend_mode),
+                };
+
+                hdr[idx] = linear.map(|c| c as f32);
+                self.raster[idx] = self.tone_map.to_color(hdr[idx]);
+            },
+        }
+
+        if opaque {
+            self.zbuffer[idx] = z;
+        }
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        x + (self.height - y - 1) * self.width
     }
 }
 
 impl fmt::Display for Image {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "P3\n{} {}\n255\n", self.width, self.height)?;
-        self.raster.iter().try_for_each(|color| write!(f, "{} ", color))?;
+        write!(f, "P3\n{} {}\n255\n", self.width(), self.height())?;
+        self.pixels().iter().try_for_each(|color| write!(f, "{} ", color))?;
         Ok(())
     }
 }
 
+// addresses the stored samples, which are only the pixels themselves without supersampling
 impl std::ops::Index<(usize, usize)> for Image {
     type Output = Color;
 
     fn index(&self, idx: (usize, usize)) -> &Self::Output {
-        &self.raster[idx.0 + (self.height - idx.1 - 1) * self.height]
+        &self.raster[self.offset(idx.0, idx.1)]
     }
 }
 
 impl std::ops::IndexMut<(usize, usize)> for Image {
     fn index_mut(&mut self, idx: (usize, usize)) -> &mut Self::Output {
-        &mut self.raster[idx.0 + (self.height - idx.1 - 1) * self.height]
+        let offset = self.offset(idx.0, idx.1);
+        &mut self.raster[offset]
     }
 }
 
@@ -155,3 +856,84 @@ fn slope(x0: i32, y0: i32, x1: i32, y1: i32) -> Option<f32> {
         Some((y1 - y0) as f32 / (x1 - x0) as f32)
     }
 }
+
+// always positive, unlike f64::fract
+fn fract(v: f64) -> f64 {
+    v - v.floor()
+}
+
+fn view(eye: Option<Vector>, p: Vector) -> Vector {
+    match eye {
+        Some(eye) => vector::subtract(eye, p),
+        None => VIEW,
+    }
+}
+
+// liang-barsky against the box from min to max, giving the visible stretch of the edge as fractions of its length
+fn clip_line(p0: Vector, p1: Vector, min: [f64; 2], max: [f64; 2]) -> Option<(f64, f64)> {
+    let d = vector::subtract(p1, p0);
+    let mut t0 = 0f64;
+    let mut t1 = 1f64;
+
+    for (p, q) in [(-d[0], p0[0] - min[0]), (d[0], max[0] - p0[0]), (-d[1], p0[1] - min[1]), (d[1], max[1] - p0[1])] {
+        if p == 0f64 {
+            // parallel to this side, so it is either entirely inside or entirely outside
+            if q < 0f64 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0f64 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+
+    if t0 > t1 || max[0] < min[0] || max[1] < min[1] {
+        return None;
+    }
+
+    Some((t0, t1))
+}
+
+// dashes and stroke widths are measured on the screen, ignoring depth
+fn planar_length(p0: Vector, p1: Vector) -> f64 {
+    (p1[0] - p0[0]).hypot(p1[1] - p0[1])
+}
+
+fn planar_direction(p0: Vector, p1: Vector) -> Option<Vector> {
+    let length = planar_length(p0, p1);
+    if length == 0f64 {
+        None
+    } else {
+        Some([(p1[0] - p0[0]) / length, (p1[1] - p0[1]) / length, 0f64])
+    }
+}
+
+// signed distance in front of the near plane in clip space
+fn near_distance(clip: [f64; 4]) -> f64 {
+    clip[2] + clip[3]
+}
+
+// a point handed to the scanline filler along with the values interpolated across the triangle
+#[derive(Copy, Clone)]
+struct Vertex<const N: usize> {
+    position: Vector,
+    attributes: [f64; N],
+}
+
+impl<const N: usize> Vertex<N> {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        let mut attributes = self.attributes;
+        for (a, b) in attributes.iter_mut().zip(other.attributes) {
+            *a += (b - *a) * t;
+        }
+
+        Self {
+            position: vector::add(self.position, vector::scale(vector::subtract(other.position, self.position), t)),
+            attributes,
+        }
+    }
+}
@@ -1,4 +1,14 @@
 pub mod math;
+mod bits;
+pub mod camera;
 pub mod color;
+pub mod compare;
+pub mod filter;
+pub mod gif;
+pub mod hdr;
 pub mod image;
-pub mod parse;
+pub mod lighting;
+pub mod mdl;
+pub mod png;
+pub mod ppm;
+pub mod stroke;
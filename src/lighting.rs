@@ -0,0 +1,124 @@
+use crate::color::Color;
+use crate::hdr;
+use crate::new_color;
+use crate::math::vector::{self, Vector};
+
+const SPECULAR_EXPONENT: f64 = 8f64;
+
+const DEFAULT_LIGHTS: [Light; 1] = [Light {
+    color: new_color!(255, 255, 255),
+    source: LightSource::Directional([0.5, 0.75, 1.0]),
+}];
+
+#[derive(Copy, Clone, Debug)]
+pub enum LightSource {
+    Point(Vector),
+    // direction from the surface toward the light
+    Directional(Vector),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Light {
+    pub color: Color,
+    pub source: LightSource,
+}
+
+// reflection constants, indexed [red, green, blue]
+#[derive(Copy, Clone, Debug)]
+pub struct Constants {
+    pub ambient: [f64; 3],
+    pub diffuse: [f64; 3],
+    pub specular: [f64; 3],
+    // 0 is invisible, 1 hides whatever is behind
+    pub opacity: f64,
+}
+
+impl Default for Constants {
+    fn default() -> Self {
+        Self {
+            ambient: [0.1; 3],
+            diffuse: [0.5; 3],
+            specular: [0.5; 3],
+            opacity: 1f64,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Lighting {
+    pub ambient: Color,
+    pub lights: Vec<Light>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: new_color!(50, 50, 50),
+            lights: Vec::new(),
+        }
+    }
+}
+
+impl Lighting {
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    // a scene without any lights of its own is lit by a single white light over the viewer's shoulder
+    fn lights(&self) -> &[Light] {
+        if self.lights.is_empty() {
+            &DEFAULT_LIGHTS
+        } else {
+            &self.lights
+        }
+    }
+
+    pub fn illuminate(&self, point: Vector, normal: Vector, view: Vector, constants: &Constants) -> Color {
+        Color::from_channels(self.intensity(point, normal, view, constants, false))
+    }
+
+    // the light leaving the surface on the 0 to 255 scale of a color, before anything gets clamped;
+    // linear decodes the light colors first, for hdr images that add up light rather than srgb bytes
+    pub fn intensity(&self, point: Vector, normal: Vector, view: Vector, constants: &Constants, linear: bool) -> [f64; 4] {
+        let normal = vector::normalize(normal);
+        let view = vector::normalize(view);
+        let ambient = channels(self.ambient, linear);
+
+        let mut intensity = [0f64; 3];
+        for i in 0..3 {
+            intensity[i] = ambient[i] * constants.ambient[i];
+        }
+
+        for light in self.lights() {
+            let to_light = match light.source {
+                LightSource::Point(position) => vector::subtract(position, point),
+                LightSource::Directional(direction) => direction,
+            };
+            let to_light = vector::normalize(to_light);
+
+            let diffuse = vector::dot(normal, to_light);
+            if diffuse <= 0f64 {
+                continue;
+            }
+
+            let reflect = vector::subtract(vector::scale(normal, 2f64 * diffuse), to_light);
+            let specular = vector::dot(reflect, view).max(0f64).powf(SPECULAR_EXPONENT);
+
+            let color = channels(light.color, linear);
+            for i in 0..3 {
+                intensity[i] += color[i] * (constants.diffuse[i] * diffuse + constants.specular[i] * specular);
+            }
+        }
+
+        [intensity[0], intensity[1], intensity[2], constants.opacity * 255f64]
+    }
+}
+
+fn channels(color: Color, linear: bool) -> [f64; 3] {
+    if linear {
+        let [r, g, b, _] = hdr::linear(color).map(|c| c as f64 * 255f64);
+        [r, g, b]
+    } else {
+        [color.red as f64, color.green as f64, color.blue as f64]
+    }
+}
@@ -102,7 +102,7 @@ impl<T: Display, const WIDTH: usize, const HEIGHT: usize> Display for ConstMatri
                 write!(f, "{:.2} ", slice[i])?;
             }
 
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -37,10 +37,14 @@ impl<T: Default + Send + Sync + Float> DynMatrix<T> {
     }
 
     pub fn add_col(&mut self, m: &Point<T>) {
-        m.into_iter().for_each(|x| self.matrix.push(*x));
+        m.iter().for_each(|x| self.matrix.push(*x));
         self.width += 1;
     }
 
+    pub fn col(&self, col: usize) -> Point<T> {
+        self.matrix[col * self.height..(col + 1) * self.height].to_vec()
+    }
+
     pub fn add_edge(&mut self, p1: &Point<T>, p2: &Point<T>) {
         self.add_col(p1);
         self.add_col(p2);
@@ -85,12 +89,12 @@ impl<T: Send + Sync + Display> Matrix for DynMatrix<T> {
     }
 }
 
-impl<T: Default + Copy> Default for DynMatrix<T> {
+impl<T> Default for DynMatrix<T> {
     fn default() -> Self {
         Self {
             width: 0,
             height: 4,
-            matrix: vec![T::default(); 0 * 4],
+            matrix: Vec::new(),
         }
     }
 }
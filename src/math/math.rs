@@ -1,8 +1,9 @@
+use std::collections::HashMap;
 use std::f64::consts::{PI, TAU};
 
 use num::{Float, NumCast};
 
-use super::{Matrix, ConstMatrix, DynMatrix};
+use super::{Matrix, ConstMatrix, DynMatrix, Vector, vector};
 
 pub type Point<T> = Vec<T>;
 
@@ -10,12 +11,14 @@ pub fn new_point<T: Float>(x: T, y: T, z: T) -> Point<T> {
     vec![x, y, z, NumCast::from(1).unwrap()]
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RotationAxis {
     X,
     Y,
     Z,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Curve {
     HERMITE,
     BEZIER,
@@ -69,90 +72,103 @@ impl<const WIDTH: usize, const HEIGHT: usize> ConstMatrix<f64, WIDTH, HEIGHT> {
     }
 }
 
+impl ConstMatrix<f64, 4, 4> {
+    pub fn transform(&self, p: [f64; 4]) -> [f64; 4] {
+        let mut t = [0f64; 4];
+        for (row, val) in t.iter_mut().enumerate() {
+            *val = (0..4).map(|col| self[(col, row)] * p[col]).sum();
+        }
+
+        t
+    }
+}
+
 impl DynMatrix<f64> {
-    pub fn add_circle(&mut self, cx: f64, cy: f64, _cz: f64, r: f64, step: f64) {
+    // the circle lies in the plane through the center facing along normal, starting out along x when the normal is z
+    pub fn add_circle(&mut self, center: Vector, normal: Vector, r: f64, step: f64) {
+        let normal = vector::normalize(normal);
+        let helper = if normal[1].abs() < 0.9 { [0f64, 1f64, 0f64] } else { [1f64, 0f64, 0f64] };
+        let u = vector::normalize(vector::cross(helper, normal));
+        let v = vector::cross(normal, u);
+
+        let point = |t: f64| {
+            let p = vector::add(center, vector::add(vector::scale(u, r * (TAU * t).cos()), vector::scale(v, r * (TAU * t).sin())));
+            new_point(p[0], p[1], p[2])
+        };
+
         let mut t = 1f64;
         while t > 0f64 {
-            let x0 = r * (TAU * t).cos() + cx;
-            let y0 = r * (TAU * t).sin() + cy;
-
+            let p0 = point(t);
             t -= step;
-
-            let x1 = r * (TAU * t).cos() + cx;
-            let y1 = r * (TAU * t).sin() + cy;
-
-            let p0 = new_point(x0, y0, 0f64);
-            let p1 = new_point(x1, y1, 0f64);
+            let p1 = point(t);
 
             self.add_edge(&p0, &p1);
         }
     }
 
-    pub fn add_curve(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64, step: f64, curve: Curve) {
-        match curve {
-            Curve::HERMITE => {
-                let inverse_h = ConstMatrix::from([
-                    [2.0, -3.0, 0.0, 1.0],
-                    [-2.0, 3.0, 0.0, 0.0],
-                    [1.0, -2.0, 1.0, 0.0],
-                    [1.0, -1.0, 0.0, 0.0],
-                ]);
-
-                let mut g_x = ConstMatrix::from([
-                    [x0, x1, x2, x3],
-                ]);
-                g_x.multiply(&inverse_h);
+    // hermite takes the two end points then their tangents, bezier the four control points in order
+    pub fn add_curve(&mut self, control: [Vector; 4], step: f64, curve: Curve) {
+        let inverse = match curve {
+            Curve::HERMITE => ConstMatrix::from([
+                [2.0, -3.0, 0.0, 1.0],
+                [-2.0, 3.0, 0.0, 0.0],
+                [1.0, -2.0, 1.0, 0.0],
+                [1.0, -1.0, 0.0, 0.0],
+            ]),
+            Curve::BEZIER => ConstMatrix::from([
+                [-1.0, 3.0, -3.0, 1.0],
+                [3.0, -6.0, 3.0, 0.0],
+                [-3.0, 3.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0, 0.0],
+            ]),
+        };
+
+        // the cubic coefficients of x, y and z, highest power first
+        let coefficients = [0, 1, 2].map(|axis| {
+            let mut g = ConstMatrix::from([
+                control.map(|p| p[axis]),
+            ]);
+            g.multiply(&inverse);
+
+            [g[(0, 0)], g[(0, 1)], g[(0, 2)], g[(0, 3)]]
+        });
+
+        let point = |t: f64| {
+            let [x, y, z] = coefficients.map(|[a, b, c, d]| a * t * t * t + b * t * t + c * t + d);
+            new_point(x, y, z)
+        };
+
+        let mut t = 0f64;
+        while t < 1f64 {
+            let p0 = point(t);
+            t += step;
+            let p1 = point(t);
 
-                let mut g_y = ConstMatrix::from([
-                    [y0, y1, y2, y3],
-                ]);
-                g_y.multiply(&inverse_h);
+            self.add_edge(&p0, &p1);
+        }
+    }
 
-                let mut t = 0f64;
-                while t < 1f64 {
-                    let x00 = g_x[(0, 0)] * t * t * t + g_x[(0, 1)] * t * t + g_x[(0, 2)] * t + g_x[(0, 3)];
-                    let y00 = g_y[(0, 0)] * t * t * t + g_y[(0, 1)] * t * t + g_y[(0, 2)] * t + g_y[(0, 3)];
+    pub fn add_polygon(&mut self, p0: &Point<f64>, p1: &Point<f64>, p2: &Point<f64>) {
+        self.add_col(p0);
+        self.add_col(p1);
+        self.add_col(p2);
+    }
 
-                    t += step;
+    // one normal per column of a polygon matrix, averaged over every face that shares the vertex
+    pub fn vertex_normals(&self) -> Vec<Vector> {
+        let key = |p: &[f64]| [p[0], p[1], p[2]].map(|v| (v * 1000f64).round() as i64);
+        let mut sums = HashMap::<[i64; 3], Vector>::new();
 
-                    let x01 = g_x[(0, 0)] * t * t * t + g_x[(0, 1)] * t * t + g_x[(0, 2)] * t + g_x[(0, 3)];
-                    let y01 = g_y[(0, 0)] * t * t * t + g_y[(0, 1)] * t * t + g_y[(0, 2)] * t + g_y[(0, 3)];
+        for chunk in self.matrix.chunks(12) {
+            let normal = vector::normalize(vector::surface_normal([chunk[0], chunk[1], chunk[2]], [chunk[4], chunk[5], chunk[6]], [chunk[8], chunk[9], chunk[10]]));
 
-                    self.add_edge(&new_point(x00, y00, 0.0), &new_point(x01, y01, 0.0));
-                }
-            },
-            Curve::BEZIER => {
-                let inverse_b = ConstMatrix::from([
-                    [-1.0, 3.0, -3.0, 1.0],
-                    [3.0, -6.0, 3.0, 0.0],
-                    [-3.0, 3.0, 0.0, 0.0],
-                    [1.0, 0.0, 0.0, 0.0],
-                ]);
-
-                let mut g_x = ConstMatrix::from([
-                    [x0, x1, x2, x3],
-                ]);
-                g_x.multiply(&inverse_b);
-
-                let mut g_y = ConstMatrix::from([
-                    [y0, y1, y2, y3],
-                ]);
-                g_y.multiply(&inverse_b);
-
-                let mut t = 0f64;
-                while t < 1f64 {
-                    let x00 = g_x[(0, 0)] * t * t * t + g_x[(0, 1)] * t * t + g_x[(0, 2)] * t + g_x[(0, 3)];
-                    let y00 = g_y[(0, 0)] * t * t * t + g_y[(0, 1)] * t * t + g_y[(0, 2)] * t + g_y[(0, 3)];
-
-                    t += step;
-
-                    let x01 = g_x[(0, 0)] * t * t * t + g_x[(0, 1)] * t * t + g_x[(0, 2)] * t + g_x[(0, 3)];
-                    let y01 = g_y[(0, 0)] * t * t * t + g_y[(0, 1)] * t * t + g_y[(0, 2)] * t + g_y[(0, 3)];
-
-                    self.add_edge(&new_point(x00, y00, 0.0), &new_point(x01, y01, 0.0));
-                }
-            },
+            for p in chunk.chunks(4) {
+                let sum = sums.entry(key(p)).or_insert([0f64; 3]);
+                *sum = vector::add(*sum, normal);
+            }
         }
+
+        self.matrix.chunks(4).map(|p| vector::normalize(sums[&key(p)])).collect()
     }
 
     pub fn add_box(&mut self, p0: Point<f64>, width: f64, height: f64, depth: f64) {
@@ -178,65 +194,162 @@ impl DynMatrix<f64> {
         self.add_edge(&p7, &p5);
     }
 
+    // triangles are wound counter-clockwise when seen from outside the box
+    pub fn add_box_polygons(&mut self, p0: Point<f64>, width: f64, height: f64, depth: f64) {
+        let p1 = new_point(p0[0] + width, p0[1], p0[2]);
+        let p2 = new_point(p0[0], p0[1] - height, p0[2]);
+        let p3 = new_point(p0[0] + width, p0[1] - height, p0[2]);
+        let p4 = new_point(p0[0], p0[1], p0[2] - depth);
+        let p5 = new_point(p0[0] + width, p0[1], p0[2] - depth);
+        let p6 = new_point(p0[0], p0[1] - height, p0[2] - depth);
+        let p7 = new_point(p0[0] + width, p0[1] - height, p0[2] - depth);
+
+        // front
+        self.add_polygon(&p0, &p2, &p3);
+        self.add_polygon(&p0, &p3, &p1);
+        // back
+        self.add_polygon(&p4, &p5, &p7);
+        self.add_polygon(&p4, &p7, &p6);
+        // top
+        self.add_polygon(&p4, &p0, &p1);
+        self.add_polygon(&p4, &p1, &p5);
+        // bottom
+        self.add_polygon(&p2, &p6, &p7);
+        self.add_polygon(&p2, &p7, &p3);
+        // left
+        self.add_polygon(&p4, &p6, &p2);
+        self.add_polygon(&p4, &p2, &p0);
+        // right
+        self.add_polygon(&p1, &p3, &p7);
+        self.add_polygon(&p1, &p7, &p5);
+    }
+
+    // `steps` half circles rotated about the x axis, each holding `steps + 1` points from pole to pole
     pub fn generate_sphere(c: Point<f64>, r: f64, step: f64) -> Self {
         let mut m = Self::default();
+        let steps = step_count(step);
+
+        for i in 0..steps {
+            let rot = i as f64 / steps as f64;
 
-        let mut rot = 0f64;
-        while rot <= 1f64 {
-            let mut cir = 0f64;
+            for j in 0..=steps {
+                let cir = j as f64 / steps as f64;
 
-            while cir <= 1f64 {
                 let x = r * (PI * cir).cos() + c[0];
                 let y = r * (PI * cir).sin() * (TAU * rot).cos() + c[1];
                 let z = r * (PI * cir).sin() * (TAU * rot).sin() + c[2];
 
                 m.add_col(&new_point(x, y, z));
-
-                cir += step;
             }
-
-            rot += step;
         }
 
         m
     }
 
+    // meridians from pole to pole, then the parallels between them; the poles themselves would only be rings of one point
     pub fn add_sphere(&mut self, c: Point<f64>, r: f64, step: f64) {
         let points = Self::generate_sphere(c, r, step);
+        let steps = step_count(step);
+        let point = |i: usize, j: usize| points.col((i % steps) * (steps + 1) + j);
+
+        for i in 0..steps {
+            for j in 0..steps {
+                self.add_edge(&point(i, j), &point(i, j + 1));
+            }
+        }
 
-        for p in points.matrix.chunks(4) {
-            self.add_edge(&new_point(p[0], p[1], p[2]), &new_point(p[0] + 1f64, p[1] + 1f64, p[2] + 1f64));
+        for j in 1..steps {
+            for i in 0..steps {
+                self.add_edge(&point(i, j), &point(i + 1, j));
+            }
+        }
+    }
+
+    // triangles are wound counter-clockwise when seen from outside the sphere
+    pub fn add_sphere_polygons(&mut self, c: Point<f64>, r: f64, step: f64) {
+        let points = Self::generate_sphere(c, r, step);
+        let steps = step_count(step);
+        let point = |i: usize, j: usize| points.col((i % steps) * (steps + 1) + j);
+
+        for i in 0..steps {
+            for j in 0..steps {
+                let p0 = point(i, j);
+                let p1 = point(i, j + 1);
+                let p2 = point(i + 1, j + 1);
+                let p3 = point(i + 1, j);
+
+                // the first and last rows of each slice meet at a pole, leaving one triangle
+                if j != steps - 1 {
+                    self.add_polygon(&p0, &p1, &p2);
+                }
+                if j != 0 {
+                    self.add_polygon(&p0, &p2, &p3);
+                }
+            }
         }
     }
 
+    // `steps` circles of `steps` points each, spaced around the center of the torus
     pub fn generate_torus(c: Point<f64>, r0: f64, r1: f64, step: f64) -> Self {
         let mut m = Self::default();
-        let mut theta = 0f64;
+        let steps = step_count(step);
+
+        for i in 0..steps {
+            let phi = i as f64 / steps as f64;
 
-        while theta <= 1f64 {
-            let mut phi = 0f64;
+            for j in 0..steps {
+                let theta = j as f64 / steps as f64;
 
-            while phi <= 1f64 {
                 let x = (phi * TAU).cos() * (r0 * (theta * TAU).cos() + r1) + c[0];
                 let y = r0 * (TAU * theta).sin() + c[1];
                 let z = -(phi * TAU).sin() * (r0 * (theta * TAU).cos() + r1) + c[2];
 
                 m.add_col(&new_point(x, y, z));
-
-                phi += step;
             }
-
-            theta += step;
         }
 
         m
     }
 
+    // the circles around the tube, then the ones running around the center
     pub fn add_torus(&mut self, c: Point<f64>, r0: f64, r1: f64, step: f64) {
-        let m = Self::generate_torus(c, r0, r1, step);
+        let points = Self::generate_torus(c, r0, r1, step);
+        let steps = step_count(step);
+        let point = |i: usize, j: usize| points.col((i % steps) * steps + j % steps);
+
+        for i in 0..steps {
+            for j in 0..steps {
+                self.add_edge(&point(i, j), &point(i, j + 1));
+            }
+        }
 
-        for p in m.matrix.chunks(4) {
-            self.add_edge(&new_point(p[0], p[1], p[2]), &new_point(p[0] + 1f64, p[1] + 1f64, p[2] + 1f64));
+        for j in 0..steps {
+            for i in 0..steps {
+                self.add_edge(&point(i, j), &point(i + 1, j));
+            }
         }
     }
+
+    // triangles are wound counter-clockwise when seen from outside the tube
+    pub fn add_torus_polygons(&mut self, c: Point<f64>, r0: f64, r1: f64, step: f64) {
+        let points = Self::generate_torus(c, r0, r1, step);
+        let steps = step_count(step);
+        let point = |i: usize, j: usize| points.col((i % steps) * steps + j % steps);
+
+        for i in 0..steps {
+            for j in 0..steps {
+                let p0 = point(i, j);
+                let p1 = point(i + 1, j);
+                let p2 = point(i + 1, j + 1);
+                let p3 = point(i, j + 1);
+
+                self.add_polygon(&p0, &p1, &p2);
+                self.add_polygon(&p0, &p2, &p3);
+            }
+        }
+    }
+}
+
+fn step_count(step: f64) -> usize {
+    ((1f64 / step).round() as usize).max(1)
 }
@@ -4,8 +4,12 @@ pub use const_matrix::ConstMatrix;
 mod dyn_matrix;
 pub use dyn_matrix::DynMatrix;
 
+#[allow(clippy::module_inception)]
 mod math;
 pub use math::{Point, new_point, RotationAxis, Curve};
 
 mod matrix;
 pub use matrix::Matrix;
+
+pub mod vector;
+pub use vector::Vector;
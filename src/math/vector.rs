@@ -0,0 +1,39 @@
+pub type Vector = [f64; 3];
+
+pub fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn subtract(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: Vector, k: f64) -> Vector {
+    [a[0] * k, a[1] * k, a[2] * k]
+}
+
+pub fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn normalize(a: Vector) -> Vector {
+    let len = dot(a, a).sqrt();
+    if len == 0f64 {
+        a
+    } else {
+        scale(a, 1f64 / len)
+    }
+}
+
+// points toward the viewer when p0, p1, p2 are counter-clockwise
+pub fn surface_normal(p0: Vector, p1: Vector, p2: Vector) -> Vector {
+    cross(subtract(p1, p0), subtract(p2, p0))
+}
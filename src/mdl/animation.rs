@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::io;
+
+use super::ast::{Command, Statement};
+use super::error;
+
+pub type Knobs = HashMap<String, f64>;
+
+pub struct Animation {
+    pub frames: usize,
+    pub basename: String,
+    // knob values for every frame
+    pub knobs: Vec<Knobs>,
+}
+
+impl Animation {
+    // first pass over the program, returns None for a still image
+    pub fn new(program: &[Statement]) -> io::Result<Option<Self>> {
+        let mut frames = None;
+        let mut basename = None;
+        let mut varies = Vec::new();
+
+        for statement in program {
+            match &statement.command {
+                Command::Frames(n) => frames = Some(*n),
+                Command::Basename(name) => basename = Some(name.clone()),
+                Command::Vary { .. } => varies.push(statement),
+                _ => (),
+            }
+        }
+
+        let frames = match frames {
+            Some(frames) => frames,
+            None => match varies.first() {
+                Some(vary) => return Err(error(vary, "vary needs a frames command")),
+                None => return Ok(None),
+            },
+        };
+
+        let basename = basename.unwrap_or_else(|| {
+            println!("No basename given, saving frames as frame000.ppm onward");
+            "frame".to_string()
+        });
+
+        let mut knobs = vec![Knobs::new(); frames];
+        let mut current = Knobs::new();
+
+        // before its first vary a knob holds that vary's start value, after its last one the end value
+        varies.sort_by_key(|statement| match statement.command {
+            Command::Vary { start_frame, .. } => start_frame,
+            _ => unreachable!(),
+        });
+        for statement in &varies {
+            if let Command::Vary { knob, end_frame, start, .. } = &statement.command {
+                if *end_frame >= frames {
+                    return Err(error(statement, &format!("vary ends on frame {} but there are only {} frames", end_frame, frames)));
+                }
+                current.entry(knob.clone()).or_insert(*start);
+            }
+        }
+
+        for (frame, values) in knobs.iter_mut().enumerate() {
+            for statement in &varies {
+                if let Command::Vary { knob, start_frame, end_frame, start, end } = &statement.command {
+                    if (*start_frame..=*end_frame).contains(&frame) {
+                        let t = if end_frame == start_frame {
+                            0f64
+                        } else {
+                            (frame - start_frame) as f64 / (end_frame - start_frame) as f64
+                        };
+                        current.insert(knob.clone(), start + (end - start) * t);
+                    }
+                }
+            }
+
+            values.clone_from(&current);
+        }
+
+        Ok(Some(Self {
+            frames,
+            basename,
+            knobs,
+        }))
+    }
+
+    pub fn filename(&self, frame: usize) -> String {
+        format!("{}{:03}.ppm", self.basename, frame)
+    }
+
+    pub fn gif_filename(&self) -> String {
+        format!("{}.gif", self.basename)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::camera::Camera;
+use crate::color::{BlendMode, Color};
+use crate::filter::ImageFilter;
+use crate::hdr::ToneMap;
+use crate::image::{Filter, Shading};
+use crate::lighting::{Constants, Light};
+use crate::math::{RotationAxis, Vector};
+use crate::stroke::{LineCap, LineJoin};
+
+#[derive(Clone, Debug)]
+pub struct Statement {
+    pub line: usize,
+    pub command: Command,
+}
+
+// optional names: `constants` picks a reflection constants set, `cs` a saved coordinate system, `knob` scales the arguments,
+// and `color` overrides the draw color for one edge shape
+#[derive(Clone, Debug)]
+pub enum Command {
+    Push,
+    Pop,
+    Move { offset: Vector, knob: Option<String> },
+    Scale { factors: Vector, knob: Option<String> },
+    Rotate { axis: RotationAxis, degrees: f64, knob: Option<String> },
+    Line { color: Option<Color>, p0: Vector, cs0: Option<String>, p1: Vector, cs1: Option<String> },
+    // the circle faces along normal
+    Circle { color: Option<Color>, center: Vector, radius: f64, normal: Vector, cs: Option<String> },
+    // the two end points then their tangents
+    Hermite { color: Option<Color>, control: [Vector; 4], cs: Option<String> },
+    Bezier { color: Option<Color>, control: [Vector; 4], cs: Option<String> },
+    // color is for wireframes, lit surfaces take theirs from the constants
+    Box { constants: Option<String>, color: Option<Color>, corner: Vector, size: Vector, cs: Option<String> },
+    Sphere { constants: Option<String>, color: Option<Color>, center: Vector, radius: f64, cs: Option<String> },
+    Torus { constants: Option<String>, color: Option<Color>, center: Vector, r0: f64, r1: f64, cs: Option<String> },
+    Constants { name: String, constants: Constants },
+    Light(Light),
+    Ambient(Color),
+    Camera(Camera),
+    // edges drawn from here on, unless a shape names its own color
+    Color(Color),
+    // clears the image to the color, which is also what later clears and new frames start from
+    Background(Color),
+    Screen { width: usize, height: usize, background: Option<Color> },
+    Blend(BlendMode),
+    Supersample { samples: usize, filter: Filter },
+    // None goes back to clamping straight into bytes
+    Hdr(Option<ToneMap>),
+    Shading(Shading),
+    Culling(bool),
+    Antialias(bool),
+    // boxes, spheres and tori as edges in the draw color instead of lit polygons
+    Wireframe(bool),
+    // fraction of a turn or curve between generated points, None goes back to each shape's own
+    Step(Option<f64>),
+    LineWidth(f64),
+    LineCap(LineCap),
+    LineJoin(LineJoin),
+    Dash(Vec<f64>),
+    SaveCoordSystem(String),
+    Frames(usize),
+    Basename(String),
+    Vary { knob: String, start_frame: usize, end_frame: usize, start: f64, end: f64 },
+    Set { knob: String, value: f64 },
+    // runs over everything drawn so far
+    Filter(ImageFilter),
+    Clear,
+    Display,
+    Save(String),
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::path::Path;
+use std::io;
+
+use crate::color::{COLOR_BLACK, COLOR_PASTEL_YELLOW};
+use crate::gif::GifEncoder;
+use crate::image::Image;
+use crate::stroke::LineStyle;
+use crate::lighting::{Constants, Lighting};
+use crate::math::{ConstMatrix, DynMatrix, new_point, Curve, Vector};
+use crate::color::Color;
+
+use super::animation::{Animation, Knobs};
+use super::ast::{Command, Statement};
+use super::error;
+
+type Transform = ConstMatrix<f64, 4, 4>;
+
+pub struct Interpreter {
+    stack: Vec<Transform>,
+    lighting: Lighting,
+    constants: HashMap<String, Constants>,
+    coord_systems: HashMap<String, Transform>,
+    knobs: Knobs,
+    background: Color,
+    draw_color: Color,
+    line_style: LineStyle,
+    wireframe: bool,
+    step: Option<f64>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self {
+            stack: vec![Transform::identity()],
+            lighting: Lighting::default(),
+            constants: HashMap::new(),
+            coord_systems: HashMap::new(),
+            knobs: Knobs::new(),
+            background: COLOR_BLACK,
+            draw_color: COLOR_PASTEL_YELLOW,
+            line_style: LineStyle::default(),
+            wireframe: false,
+            step: None,
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn run(&mut self, image: &mut Image, program: &[Statement]) -> io::Result<()> {
+        let animation = match Animation::new(program)? {
+            Some(animation) => animation,
+            None => return self.render(image, program),
+        };
+
+        if let Some(parent) = Path::new(&animation.filename(0)).parent() {
+            create_dir_all(parent)?;
+        }
+
+        let mut frames = Vec::with_capacity(animation.frames);
+        for (frame, knobs) in animation.knobs.iter().enumerate() {
+            *self = Self {
+                knobs: knobs.clone(),
+                ..Self::default()
+            };
+            image.reset(COLOR_BLACK);
+
+            self.render(image, program)?;
+            image.save(&animation.filename(frame))?;
+            frames.push(image.clone());
+        }
+
+        GifEncoder::default().save(&frames, &animation.gif_filename())
+    }
+
+    fn render(&mut self, image: &mut Image, program: &[Statement]) -> io::Result<()> {
+        for statement in program {
+            self.execute(image, statement)?;
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, image: &mut Image, statement: &Statement) -> io::Result<()> {
+        match &statement.command {
+            Command::Push => {
+                self.stack.push(*self.top());
+            },
+            Command::Pop => {
+                self.stack.pop();
+                if self.stack.is_empty() {
+                    self.stack.push(Transform::identity());
+                }
+            },
+            Command::Move { offset, knob } => {
+                let k = self.knob(statement, knob)?;
+                self.transform(Transform::make_translate(offset[0] * k, offset[1] * k, offset[2] * k));
+            },
+            Command::Scale { factors, knob } => {
+                let k = self.knob(statement, knob)?;
+                self.transform(Transform::make_scale(factors[0] * k, factors[1] * k, factors[2] * k));
+            },
+            Command::Rotate { axis, degrees, knob } => {
+                let k = self.knob(statement, knob)?;
+                self.transform(Transform::make_rotate(*axis, degrees * k));
+            },
+            Command::Line { color, p0, cs0, p1, cs1 } => {
+                let p0 = self.coord_system(statement, cs0)?.transform([p0[0], p0[1], p0[2], 1f64]);
+                let p1 = self.coord_system(statement, cs1)?.transform([p1[0], p1[1], p1[2], 1f64]);
+
+                let mut edges = DynMatrix::default();
+                edges.add_edge(&p0.to_vec(), &p1.to_vec());
+                image.draw_lines(&edges, color.unwrap_or(self.draw_color), &self.line_style);
+            },
+            Command::Circle { color, center, radius, normal, cs } => {
+                let mut edges = DynMatrix::default();
+                edges.add_circle(*center, *normal, *radius, self.step.unwrap_or(0.05));
+                self.draw_edges(image, statement, color, cs, edges)?;
+            },
+            Command::Hermite { color, control, cs } => {
+                let mut edges = DynMatrix::default();
+                edges.add_curve(*control, self.step.unwrap_or(0.05), Curve::HERMITE);
+                self.draw_edges(image, statement, color, cs, edges)?;
+            },
+            Command::Bezier { color, control, cs } => {
+                let mut edges = DynMatrix::default();
+                edges.add_curve(*control, self.step.unwrap_or(0.05), Curve::BEZIER);
+                self.draw_edges(image, statement, color, cs, edges)?;
+            },
+            Command::Box { constants, color, corner, size, cs } => {
+                let (constants, color) = self.material(constants, *color);
+                let mut m = DynMatrix::default();
+                if self.wireframe {
+                    m.add_box(point(corner), size[0], size[1], size[2]);
+                    self.draw_edges(image, statement, &color, cs, m)?;
+                } else {
+                    m.add_box_polygons(point(corner), size[0], size[1], size[2]);
+                    self.draw_polygons(image, statement, &constants, cs, m)?;
+                }
+            },
+            Command::Sphere { constants, color, center, radius, cs } => {
+                let (constants, color) = self.material(constants, *color);
+                let step = self.step.unwrap_or(0.05);
+                let mut m = DynMatrix::default();
+                if self.wireframe {
+                    m.add_sphere(point(center), *radius, step);
+                    self.draw_edges(image, statement, &color, cs, m)?;
+                } else {
+                    m.add_sphere_polygons(point(center), *radius, step);
+                    self.draw_polygons(image, statement, &constants, cs, m)?;
+                }
+            },
+            Command::Torus { constants, color, center, r0, r1, cs } => {
+                let (constants, color) = self.material(constants, *color);
+                let step = self.step.unwrap_or(0.06);
+                let mut m = DynMatrix::default();
+                if self.wireframe {
+                    m.add_torus(point(center), *r0, *r1, step);
+                    self.draw_edges(image, statement, &color, cs, m)?;
+                } else {
+                    m.add_torus_polygons(point(center), *r0, *r1, step);
+                    self.draw_polygons(image, statement, &constants, cs, m)?;
+                }
+            },
+            Command::Constants { name, constants } => {
+                self.constants.insert(name.clone(), *constants);
+            },
+            Command::Light(light) => {
+                self.lighting.add_light(*light);
+            },
+            Command::Ambient(color) => {
+                self.lighting.ambient = *color;
+            },
+            Command::Color(color) => {
+                self.draw_color = *color;
+            },
+            Command::Background(color) => {
+                self.background = *color;
+                image.reset(self.background);
+            },
+            Command::Camera(camera) => {
+                image.set_camera(Some(*camera));
+            },
+            Command::Screen { width, height, background } => {
+                if let Some(background) = background {
+                    self.background = *background;
+                }
+                image.resize(*width, *height, self.background);
+            },
+            Command::Blend(mode) => {
+                image.set_blend_mode(*mode);
+            },
+            Command::Supersample { samples, filter } => {
+                image.set_supersampling(*samples, *filter);
+            },
+            Command::Hdr(tone_map) => {
+                image.set_hdr(*tone_map);
+            },
+            Command::Shading(shading) => {
+                image.set_shading(*shading);
+            },
+            Command::Culling(culling) => {
+                image.set_culling(*culling);
+            },
+            Command::Antialias(antialias) => {
+                self.line_style.antialias = *antialias;
+            },
+            Command::Wireframe(wireframe) => {
+                self.wireframe = *wireframe;
+            },
+            Command::Step(step) => {
+                self.step = *step;
+            },
+            Command::LineWidth(width) => {
+                self.line_style.width = *width;
+            },
+            Command::LineCap(cap) => {
+                self.line_style.cap = *cap;
+            },
+            Command::LineJoin(join) => {
+                self.line_style.join = *join;
+            },
+            Command::Dash(dash) => {
+                self.line_style.dash.clone_from(dash);
+            },
+            Command::SaveCoordSystem(name) => {
+                self.coord_systems.insert(name.clone(), *self.top());
+            },
+            Command::Set { knob, value } => {
+                self.knobs.insert(knob.clone(), *value);
+            },
+            Command::Frames(_) | Command::Basename(_) | Command::Vary { .. } => (),
+            Command::Filter(filter) => {
+                image.apply_filter(filter);
+            },
+            Command::Clear => {
+                image.reset(self.background);
+            },
+            Command::Display => {
+                println!("Unable to display anything on my pc due to using WSL");
+            },
+            Command::Save(filename) => {
+                image.save(filename)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn top(&self) -> &Transform {
+        self.stack.last().unwrap()
+    }
+
+    // the new transform applies in the coordinate system on top of the stack
+    fn transform(&mut self, mut m: Transform) {
+        let top = self.stack.last_mut().unwrap();
+        m.multiply(top);
+        *top = m;
+    }
+
+    fn knob(&self, statement: &Statement, name: &Option<String>) -> io::Result<f64> {
+        match name {
+            Some(name) => self.knobs.get(name).copied().ok_or_else(|| error(statement, &format!("unknown knob '{}'", name))),
+            None => Ok(1f64),
+        }
+    }
+
+    fn coord_system(&self, statement: &Statement, name: &Option<String>) -> io::Result<&Transform> {
+        match name {
+            Some(name) => self.coord_systems.get(name).ok_or_else(|| error(statement, &format!("unknown coordinate system '{}'", name))),
+            None => Ok(self.top()),
+        }
+    }
+
+    fn draw_edges(&self, image: &mut Image, statement: &Statement, color: &Option<Color>, cs: &Option<String>, mut edges: DynMatrix<f64>) -> io::Result<()> {
+        edges.multiply(self.coord_system(statement, cs)?);
+        image.draw_lines(&edges, color.unwrap_or(self.draw_color), &self.line_style);
+
+        Ok(())
+    }
+
+    // a lone word that names no constants but does name a color is the shape's color
+    fn material(&self, constants: &Option<String>, color: Option<Color>) -> (Option<String>, Option<Color>) {
+        match constants {
+            Some(name) if color.is_none() && !self.constants.contains_key(name) => match Color::parse(name) {
+                Some(color) => (None, Some(color)),
+                None => (constants.clone(), None),
+            },
+            _ => (constants.clone(), color),
+        }
+    }
+
+    fn draw_polygons(&self, image: &mut Image, statement: &Statement, constants: &Option<String>, cs: &Option<String>, mut polygons: DynMatrix<f64>) -> io::Result<()> {
+        let constants = match constants {
+            Some(name) => *self.constants.get(name).ok_or_else(|| error(statement, &format!("unknown constants '{}'", name)))?,
+            None => Constants::default(),
+        };
+
+        polygons.multiply(self.coord_system(statement, cs)?);
+        image.draw_polygons(&polygons, &self.lighting, &constants);
+
+        Ok(())
+    }
+}
+
+fn point(v: &Vector) -> Vec<f64> {
+    new_point(v[0], v[1], v[2])
+}
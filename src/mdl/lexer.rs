@@ -0,0 +1,91 @@
+use super::ParseError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Word(String),
+    Number(f64),
+    Newline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+}
+
+// splits the source into whitespace separated words and numbers, keeping line breaks since they end commands;
+// anything between parentheses stays in one word so that hsv(0, 1, 1) reads as a single color
+pub fn lex(source: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                chars.next();
+                tokens.push(Token { kind: TokenKind::Newline, line });
+                line += 1;
+            },
+            c if c.is_whitespace() => {
+                chars.next();
+            },
+            '/' if starts_comment(&chars) => {
+                chars.next();
+                match chars.next() {
+                    Some('/') => {
+                        while chars.next_if(|&c| c != '\n').is_some() {}
+                    },
+                    _ => {
+                        let start = line;
+                        let mut last = ' ';
+                        loop {
+                            match chars.next() {
+                                Some('/') if last == '*' => break,
+                                Some(c) => {
+                                    if c == '\n' {
+                                        line += 1;
+                                    }
+                                    last = c;
+                                },
+                                None => return Err(ParseError::new(start, "unterminated block comment")),
+                            }
+                        }
+                    },
+                }
+            },
+            _ => {
+                let mut word = String::new();
+                let mut depth = 0usize;
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' || (depth == 0 && (c.is_whitespace() || (c == '/' && starts_comment(&chars)))) {
+                        break;
+                    }
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth = depth.saturating_sub(1),
+                        _ => (),
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                let kind = match word.parse::<f64>() {
+                    Ok(n) if word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) => TokenKind::Number(n),
+                    _ => TokenKind::Word(word),
+                };
+                tokens.push(Token { kind, line });
+            },
+        }
+    }
+
+    tokens.push(Token { kind: TokenKind::Newline, line });
+
+    Ok(tokens)
+}
+
+fn starts_comment(chars: &std::iter::Peekable<std::str::Chars>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    matches!(ahead.next(), Some('/') | Some('*'))
+}
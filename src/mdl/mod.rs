@@ -0,0 +1,30 @@
+use std::fs;
+use std::io;
+
+use crate::image::Image;
+
+mod lexer;
+
+mod ast;
+pub use ast::{Command, Statement};
+
+mod parser;
+pub use parser::{parse, ParseError};
+
+mod animation;
+pub use animation::{Animation, Knobs};
+
+mod interpreter;
+pub use interpreter::Interpreter;
+
+pub fn run(image: &mut Image, filename: &str) -> io::Result<()> {
+    let source = fs::read_to_string(filename)?;
+    let program = parse(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Interpreter::default().run(image, &program)
+}
+
+// a problem with a statement that parsed fine, reported against its line
+pub(super) fn error(statement: &Statement, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", statement.line, message))
+}
@@ -0,0 +1,532 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::camera::Camera;
+use crate::hdr::ToneMap;
+use crate::image::{Filter, Shading};
+use crate::lighting::{Constants, Light, LightSource};
+use crate::math::{RotationAxis, Vector};
+use crate::stroke::{LineCap, LineJoin};
+use crate::color::{BlendMode, Color};
+use crate::filter::{ImageFilter, Kernel, MAX_MEDIAN_RADIUS};
+use crate::new_color;
+
+use super::ast::{Command, Statement};
+use super::lexer::{lex, Token, TokenKind};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: &str) -> Self {
+        Self {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+pub fn parse(source: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        pos: 0,
+    };
+    let mut program = Vec::new();
+
+    while parser.pos < parser.tokens.len() {
+        if parser.peek() == &TokenKind::Newline {
+            parser.pos += 1;
+            continue;
+        }
+
+        let line = parser.line();
+        let command = parser.command()?;
+        parser.end()?;
+
+        program.push(Statement { line, command });
+    }
+
+    Ok(program)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn command(&mut self) -> Result<Command, ParseError> {
+        let name = self.word()?;
+
+        let command = match name.as_str() {
+            "push" => Command::Push,
+            "pop" => Command::Pop,
+            "move" => Command::Move {
+                offset: self.vector()?,
+                knob: self.optional_word(),
+            },
+            "scale" => Command::Scale {
+                factors: self.vector()?,
+                knob: self.optional_word(),
+            },
+            "rotate" => {
+                let axis = match self.word()?.as_str() {
+                    "x" => RotationAxis::X,
+                    "y" => RotationAxis::Y,
+                    "z" => RotationAxis::Z,
+                    axis => return Err(self.error(&format!("unknown rotation axis '{}'", axis))),
+                };
+
+                Command::Rotate {
+                    axis,
+                    degrees: self.number()?,
+                    knob: self.optional_word(),
+                }
+            },
+            "line" => Command::Line {
+                color: self.optional_color(),
+                p0: self.vector()?,
+                cs0: self.optional_word(),
+                p1: self.vector()?,
+                cs1: self.optional_word(),
+            },
+            "circle" => {
+                let color = self.optional_color();
+                let center = self.vector()?;
+                let radius = self.number()?;
+
+                // an optional normal tilts the circle out of the xy plane
+                let normal = match self.peek() {
+                    TokenKind::Number(_) => self.vector()?,
+                    _ => [0f64, 0f64, 1f64],
+                };
+                if normal == [0f64; 3] {
+                    return Err(self.error("circle normal cannot be zero"));
+                }
+
+                Command::Circle { color, center, radius, normal, cs: self.optional_word() }
+            },
+            "hermite" => Command::Hermite {
+                color: self.optional_color(),
+                control: self.control()?,
+                cs: self.optional_word(),
+            },
+            "bezier" => Command::Bezier {
+                color: self.optional_color(),
+                control: self.control()?,
+                cs: self.optional_word(),
+            },
+            "box" => Command::Box {
+                constants: self.optional_word(),
+                color: self.optional_color(),
+                corner: self.vector()?,
+                size: self.vector()?,
+                cs: self.optional_word(),
+            },
+            "sphere" => Command::Sphere {
+                constants: self.optional_word(),
+                color: self.optional_color(),
+                center: self.vector()?,
+                radius: self.number()?,
+                cs: self.optional_word(),
+            },
+            "torus" => Command::Torus {
+                constants: self.optional_word(),
+                color: self.optional_color(),
+                center: self.vector()?,
+                r0: self.number()?,
+                r1: self.number()?,
+                cs: self.optional_word(),
+            },
+            "constants" => {
+                let name = self.word()?;
+                let [kar, kdr, ksr, kag, kdg, ksg, kab, kdb, ksb] = self.numbers()?;
+
+                // an optional tenth value makes the material see-through
+                let opacity = match self.peek() {
+                    TokenKind::Number(_) => self.number()?,
+                    _ => 1f64,
+                };
+                if !(0f64..=1f64).contains(&opacity) {
+                    return Err(self.error("opacity must be between 0 and 1"));
+                }
+
+                Command::Constants {
+                    name,
+                    constants: Constants {
+                        ambient: [kar, kag, kab],
+                        diffuse: [kdr, kdg, kdb],
+                        specular: [ksr, ksg, ksb],
+                        opacity,
+                    },
+                }
+            },
+            "light" => {
+                // the type is optional and the color can be a word too, so only the two type names count as one
+                let point = match self.peek() {
+                    TokenKind::Word(kind) if kind == "point" || kind == "directional" => self.word()? == "point",
+                    _ => false,
+                };
+                let color = self.opaque_color()?;
+                let location = self.vector()?;
+
+                let source = if point {
+                    LightSource::Point(location)
+                } else {
+                    LightSource::Directional(location)
+                };
+
+                Command::Light(Light { color, source })
+            },
+            "ambient" => Command::Ambient(self.opaque_color()?),
+            "color" => Command::Color(self.color()?),
+            "background" => Command::Background(self.color()?),
+            "camera" => {
+                let mut camera = Camera::new(self.vector()?, self.vector()?);
+
+                if let TokenKind::Number(_) = self.peek() {
+                    camera.up = self.vector()?;
+                }
+                if let TokenKind::Number(_) = self.peek() {
+                    camera.fov = self.number()?;
+                    camera.near = self.number()?;
+                    camera.far = self.number()?;
+                }
+
+                Command::Camera(camera)
+            },
+            "screen" => {
+                let width = self.index()?;
+                let height = self.index()?;
+                if width == 0 || height == 0 {
+                    return Err(self.error("the screen needs a width and height of at least one pixel"));
+                }
+
+                let background = match self.peek() {
+                    TokenKind::Newline => None,
+                    _ => Some(self.color()?),
+                };
+
+                Command::Screen { width, height, background }
+            },
+            "shading" => Command::Shading(match self.word()?.as_str() {
+                "flat" => Shading::Flat,
+                "gouraud" => Shading::Gouraud,
+                "phong" => Shading::Phong,
+                mode => return Err(self.error(&format!("unknown shading mode '{}'", mode))),
+            }),
+            "blend" => Command::Blend(match self.word()?.as_str() {
+                "normal" => BlendMode::Normal,
+                "multiply" => BlendMode::Multiply,
+                "screen" => BlendMode::Screen,
+                "add" => BlendMode::Add,
+                mode => return Err(self.error(&format!("unknown blend mode '{}'", mode))),
+            }),
+            "supersample" => {
+                let samples = self.index()?;
+                if samples == 0 {
+                    return Err(self.error("supersampling needs at least one sample per pixel"));
+                }
+
+                let filter = match self.optional_word().as_deref() {
+                    None | Some("box") => Filter::Box,
+                    Some("tent") => Filter::Tent,
+                    Some(filter) => return Err(self.error(&format!("unknown filter '{}'", filter))),
+                };
+
+                Command::Supersample { samples, filter }
+            },
+            "hdr" => Command::Hdr(match self.word()?.as_str() {
+                "reinhard" => Some(ToneMap::Reinhard),
+                "aces" => Some(ToneMap::Aces),
+                "off" => None,
+                mode => return Err(self.error(&format!("hdr must be 'reinhard', 'aces' or 'off', found '{}'", mode))),
+            }),
+            "culling" => Command::Culling(self.switch("culling")?),
+            "antialias" => Command::Antialias(self.switch("antialias")?),
+            "wireframe" => Command::Wireframe(self.switch("wireframe")?),
+            // no step goes back to the defaults
+            "step" => match self.peek() {
+                TokenKind::Number(_) => {
+                    let step = self.number()?;
+                    if step <= 0f64 || step > 1f64 {
+                        return Err(self.error("step must be more than 0 and at most 1"));
+                    }
+
+                    Command::Step(Some(step))
+                },
+                _ => Command::Step(None),
+            },
+            "line_width" => {
+                let width = self.number()?;
+                if width <= 0f64 {
+                    return Err(self.error("line width must be positive"));
+                }
+
+                Command::LineWidth(width)
+            },
+            "line_cap" => Command::LineCap(match self.word()?.as_str() {
+                "butt" => LineCap::Butt,
+                "round" => LineCap::Round,
+                "square" => LineCap::Square,
+                cap => return Err(self.error(&format!("unknown line cap '{}'", cap))),
+            }),
+            "line_join" => Command::LineJoin(match self.word()?.as_str() {
+                "miter" => LineJoin::Miter,
+                "round" => LineJoin::Round,
+                "bevel" => LineJoin::Bevel,
+                join => return Err(self.error(&format!("unknown line join '{}'", join))),
+            }),
+            // no lengths goes back to solid lines
+            "dash" => {
+                let mut dash = Vec::new();
+                while let TokenKind::Number(_) = self.peek() {
+                    dash.push(self.number()?);
+                }
+                if dash.iter().any(|&length| length < 0f64) {
+                    return Err(self.error("dash lengths cannot be negative"));
+                }
+
+                Command::Dash(dash)
+            },
+            "save_coord_system" => Command::SaveCoordSystem(self.word()?),
+            "frames" => {
+                let frames = self.index()?;
+                if frames == 0 {
+                    return Err(self.error("an animation needs at least one frame"));
+                }
+
+                Command::Frames(frames)
+            },
+            "basename" => Command::Basename(self.word()?),
+            "vary" => {
+                let knob = self.word()?;
+                let start_frame = self.index()?;
+                let end_frame = self.index()?;
+                if end_frame < start_frame {
+                    return Err(self.error("vary must end on or after its start frame"));
+                }
+
+                Command::Vary {
+                    knob,
+                    start_frame,
+                    end_frame,
+                    start: self.number()?,
+                    end: self.number()?,
+                }
+            },
+            "set" => Command::Set {
+                knob: self.word()?,
+                value: self.number()?,
+            },
+            "convolve" => {
+                let width = self.index()?;
+                let height = self.index()?;
+                if width % 2 == 0 || height % 2 == 0 {
+                    return Err(self.error("kernel sides must be odd"));
+                }
+
+                // every weight is a token of its own, so a kernel can't be bigger than what is left of the script
+                let count = width.checked_mul(height)
+                    .filter(|&count| count <= self.tokens.len() - self.pos)
+                    .ok_or_else(|| self.error(&format!("a {}x{} kernel needs more weights than there are", width, height)))?;
+                let weights = (0..count).map(|_| self.number()).collect::<Result<Vec<_>, _>>()?;
+
+                Command::Filter(ImageFilter::Convolve(Kernel::new(width, height, weights)))
+            },
+            "blur" => Command::Filter(ImageFilter::GaussianBlur(self.sigma()?)),
+            "sharpen" => {
+                let sigma = self.sigma()?;
+                let amount = match self.peek() {
+                    TokenKind::Number(_) => self.number()?,
+                    _ => 1f64,
+                };
+
+                Command::Filter(ImageFilter::UnsharpMask { sigma, amount })
+            },
+            "edges" => Command::Filter(ImageFilter::Sobel),
+            "median" => {
+                let radius = self.index()?;
+                if radius > MAX_MEDIAN_RADIUS {
+                    return Err(self.error(&format!("median radius can be at most {}", MAX_MEDIAN_RADIUS)));
+                }
+
+                Command::Filter(ImageFilter::Median(radius))
+            },
+            "clear" => Command::Clear,
+            "display" => Command::Display,
+            "save" => Command::Save(self.word()?),
+            unknown => return Err(self.error(&format!("unknown command '{}'", unknown))),
+        };
+
+        Ok(command)
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.pos].kind
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].line
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::new(self.line(), message)
+    }
+
+    fn end(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            TokenKind::Newline => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => Err(self.error(&format!("unexpected {}", describe(self.peek())))),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, ParseError> {
+        match self.peek().clone() {
+            TokenKind::Word(word) => {
+                self.pos += 1;
+                Ok(word)
+            },
+            kind => Err(self.error(&format!("expected a name, found {}", describe(&kind)))),
+        }
+    }
+
+    fn optional_word(&mut self) -> Option<String> {
+        match self.peek() {
+            TokenKind::Word(_) => self.word().ok(),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        match *self.peek() {
+            TokenKind::Number(n) => {
+                self.pos += 1;
+                Ok(n)
+            },
+            ref kind => Err(self.error(&format!("expected a number, found {}", describe(kind)))),
+        }
+    }
+
+    fn index(&mut self) -> Result<usize, ParseError> {
+        let n = self.number()?;
+        if n < 0f64 || n.fract() != 0f64 {
+            return Err(self.error(&format!("expected a whole number, found '{}'", n)));
+        }
+
+        Ok(n as usize)
+    }
+
+    fn numbers<const N: usize>(&mut self) -> Result<[f64; N], ParseError> {
+        let mut values = [0f64; N];
+        for value in values.iter_mut() {
+            *value = self.number()?;
+        }
+
+        Ok(values)
+    }
+
+    fn vector(&mut self) -> Result<Vector, ParseError> {
+        self.numbers()
+    }
+
+    // four points as x y pairs in the z = 0 plane, or as x y z triples
+    fn control(&mut self) -> Result<[Vector; 4], ParseError> {
+        let mut values = Vec::with_capacity(12);
+        while let TokenKind::Number(_) = self.peek() {
+            values.push(self.number()?);
+        }
+
+        match values.len() {
+            8 => Ok([0, 1, 2, 3].map(|i| [values[2 * i], values[2 * i + 1], 0f64])),
+            12 => Ok([0, 1, 2, 3].map(|i| [values[3 * i], values[3 * i + 1], values[3 * i + 2]])),
+            n => Err(self.error(&format!("curves take 8 numbers in 2d or 12 in 3d, found {}", n))),
+        }
+    }
+
+    fn sigma(&mut self) -> Result<f64, ParseError> {
+        let sigma = self.number()?;
+        if sigma <= 0f64 {
+            return Err(self.error("blur radius must be positive"));
+        }
+
+        Ok(sigma)
+    }
+
+    fn switch(&mut self, command: &str) -> Result<bool, ParseError> {
+        match self.word()?.as_str() {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            mode => Err(self.error(&format!("{} must be 'on' or 'off', found '{}'", command, mode))),
+        }
+    }
+
+    // a color name, hex code or hsv(...) as one word, or red, green and blue with an optional alpha, all between 0 and 255
+    fn color(&mut self) -> Result<Color, ParseError> {
+        if let TokenKind::Word(word) = self.peek() {
+            let color = Color::parse(word).ok_or_else(|| self.error(&format!("unknown color '{}'", word)))?;
+            self.pos += 1;
+            return Ok(color);
+        }
+
+        let color = self.opaque_color()?;
+        match self.peek() {
+            TokenKind::Number(_) => Ok(color.with_alpha(self.channel()?)),
+            _ => Ok(color),
+        }
+    }
+
+    // lights take exactly three numbers, since numbers right after them are where the light is
+    fn opaque_color(&mut self) -> Result<Color, ParseError> {
+        if let TokenKind::Word(word) = self.peek() {
+            let color = Color::parse(word).ok_or_else(|| self.error(&format!("unknown color '{}'", word)))?;
+            self.pos += 1;
+            return Ok(color.with_alpha(255));
+        }
+
+        Ok(new_color!(self.channel()?, self.channel()?, self.channel()?))
+    }
+
+    fn channel(&mut self) -> Result<u8, ParseError> {
+        let value = self.number()?;
+        if !(0f64..=255f64).contains(&value) {
+            return Err(self.error("color channels must be between 0 and 255"));
+        }
+
+        Ok(value.round() as u8)
+    }
+
+    // only a word that reads as a color, numbers are left for whatever follows
+    fn optional_color(&mut self) -> Option<Color> {
+        match self.peek() {
+            TokenKind::Word(word) => {
+                let color = Color::parse(word)?;
+                self.pos += 1;
+                Some(color)
+            },
+            _ => None,
+        }
+    }
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) => format!("'{}'", word),
+        TokenKind::Number(n) => format!("'{}'", n),
+        TokenKind::Newline => "end of line".to_string(),
+    }
+}
@@ -0,0 +1,396 @@
+use crate::bits::BitWriter;
+use crate::image::Image;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+pub fn encode(image: &Image) -> Vec<u8> {
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height() as u32).to_be_bytes());
+    // 8 bit truecolor, with alpha only when something is see-through, deflate, adaptive filtering, no interlace
+    let alpha = image.pixels().iter().any(|c| c.alpha != 255);
+    header.extend_from_slice(&[8, if alpha { 6 } else { 2 }, 0, 0, 0]);
+
+    let bpp = if alpha { 4 } else { 3 };
+    let rows = image.pixels()
+        .chunks(image.width().max(1))
+        .map(|row| row.iter().flat_map(|c| [c.red, c.green, c.blue, c.alpha].into_iter().take(bpp)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib(&filter(&rows, bpp)));
+    chunk(&mut out, b"IEND", &[]);
+
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+
+    table
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |c, &b| CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8))
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    b << 16 | a
+}
+
+// prefixes every row with the filter type that leaves the smallest absolute residuals
+fn filter(rows: &[Vec<u8>], bpp: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rows.iter().map(|row| row.len() + 1).sum());
+    let empty = vec![0u8; rows.first().map_or(0, |row| row.len())];
+
+    for (i, row) in rows.iter().enumerate() {
+        let prior = if i == 0 { &empty } else { &rows[i - 1] };
+
+        let best = (0..5u8).map(|kind| {
+            let filtered = row.iter().enumerate().map(|(x, &byte)| {
+                let a = if x >= bpp { row[x - bpp] } else { 0 };
+                let b = prior[x];
+                let c = if x >= bpp { prior[x - bpp] } else { 0 };
+
+                byte.wrapping_sub(match kind {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                })
+            }).collect::<Vec<_>>();
+
+            (kind, filtered)
+        }).min_by_key(|(_, filtered)| filtered.iter().map(|&v| (v as i8).unsigned_abs() as u32).sum::<u32>());
+
+        if let Some((kind, filtered)) = best {
+            out.push(kind);
+            out.extend_from_slice(&filtered);
+        }
+    }
+
+    out
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x9c];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+// a single fixed huffman block, with repeats found through hash chains over the last 32k
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let hash = |i: usize| ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7fff;
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; WINDOW];
+
+    let insert = |i: usize, head: &mut Vec<usize>, prev: &mut Vec<usize>| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            prev[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let max = MAX_MATCH.min(data.len() - i);
+                let length = (0..max).take_while(|&k| data[candidate + k] == data[i + k]).count();
+                if length > best.0 {
+                    best = (length, i - candidate);
+                    if length == max {
+                        break;
+                    }
+                }
+
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best.0 >= MIN_MATCH {
+            write_length(&mut writer, best.0);
+            write_distance(&mut writer, best.1);
+            for k in i..i + best.0 {
+                insert(k, &mut head, &mut prev);
+            }
+            i += best.0;
+        } else {
+            write_literal(&mut writer, data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+
+    write_literal(&mut writer, 256);
+
+    writer.finish()
+}
+
+// huffman codes go out most significant bit first, unlike everything else in the stream
+fn write_code(writer: &mut BitWriter, code: u16, size: u8) {
+    writer.write(code.reverse_bits() >> (16 - size), size);
+}
+
+fn write_literal(writer: &mut BitWriter, symbol: u16) {
+    match symbol {
+        0..=143 => write_code(writer, 0x30 + symbol, 8),
+        144..=255 => write_code(writer, 0x190 + symbol - 144, 9),
+        256..=279 => write_code(writer, symbol - 256, 7),
+        _ => write_code(writer, 0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let code = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+    write_literal(writer, 257 + code as u16);
+    writer.write((length - LENGTH_BASE[code] as usize) as u16, LENGTH_EXTRA[code]);
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let code = DIST_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+    write_code(writer, code as u16, 5);
+    writer.write((distance - DIST_BASE[code] as usize) as u16, DIST_EXTRA[code]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    // reads bits least significant first, the way BitWriter packs them
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, size: u8) -> usize {
+            (0..size).map(|k| {
+                let bit = self.data[self.pos / 8] >> (self.pos % 8) & 1;
+                self.pos += 1;
+                (bit as usize) << k
+            }).sum()
+        }
+
+        fn code(&mut self, size: u8) -> usize {
+            (0..size).fold(0, |code, _| code << 1 | self.bits(1))
+        }
+
+        fn symbol(&mut self) -> usize {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return code + 256;
+            }
+
+            let code = code << 1 | self.bits(1);
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => code - 0xc0 + 280,
+                _ => (code << 1 | self.bits(1)) - 0x190 + 144,
+            }
+        }
+    }
+
+    // only the single fixed huffman block deflate writes
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader { data, pos: 0 };
+        assert_eq!(reader.bits(3), 0b011, "expected one final fixed huffman block");
+
+        let mut out = Vec::new();
+        loop {
+            match reader.symbol() {
+                literal @ 0..=255 => out.push(literal as u8),
+                256 => return out,
+                symbol => {
+                    let code = symbol - 257;
+                    let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code]);
+                    let code = reader.code(5);
+                    let distance = DIST_BASE[code] as usize + reader.bits(DIST_EXTRA[code]);
+
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                },
+            }
+        }
+    }
+
+    fn unfilter(data: &[u8], width: usize, bpp: usize) -> Vec<u8> {
+        let stride = width * bpp;
+        let mut out: Vec<u8> = Vec::new();
+
+        for (y, row) in data.chunks(stride + 1).enumerate() {
+            let start = out.len();
+            for (x, &byte) in row[1..].iter().enumerate() {
+                let a = if x >= bpp { out[start + x - bpp] } else { 0 };
+                let b = if y > 0 { out[start + x - stride] } else { 0 };
+                let c = if x >= bpp && y > 0 { out[start + x - bpp - stride] } else { 0 };
+
+                out.push(byte.wrapping_add(match row[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    kind => panic!("unknown filter type {}", kind),
+                }));
+            }
+        }
+
+        out
+    }
+
+    // the color type and the raw samples, after checking every chunk's crc and the zlib checksum
+    fn decode(png: &[u8]) -> (u8, Vec<u8>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let body = &png[pos + 4..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc, "bad crc on a {} chunk", String::from_utf8_lossy(&body[..4]));
+
+            chunks.push((body[..4].to_vec(), body[4..].to_vec()));
+            pos += len + 12;
+        }
+
+        let kinds = chunks.iter().map(|(kind, _)| kind.as_slice()).collect::<Vec<_>>();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let header = &chunks[0].1;
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let color_type = header[9];
+
+        let zlib = &chunks[1].1;
+        assert_eq!(zlib[..2], [0x78, 0x9c]);
+        let filtered = inflate(&zlib[2..zlib.len() - 4]);
+        assert_eq!(adler32(&filtered).to_be_bytes(), zlib[zlib.len() - 4..]);
+
+        (color_type, unfilter(&filtered, width, if color_type == 6 { 4 } else { 3 }))
+    }
+
+    fn image(alpha: u8) -> Image {
+        let pixels = (0..12 * 9).map(|i| Color { red: (i * 7) as u8, green: (i % 12 * 20) as u8, blue: 90, alpha: if i % 5 == 0 { alpha } else { 255 } }).collect();
+        Image::from_pixels(12, 9, pixels)
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn fixed_huffman_stream() {
+        // the final fixed block bits, then the 7 zero bits of end of block
+        assert_eq!(deflate(&[]), [0x03, 0x00]);
+        // 'a' is the 8 bit code 0x91
+        assert_eq!(deflate(b"a"), [0x4b, 0x04, 0x00]);
+
+        let data = b"pepper pepper pepper, peppers and more peppers".repeat(20);
+        let compressed = deflate(&data);
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(inflate(&compressed), data);
+
+        let noise = (0..5000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect::<Vec<_>>();
+        assert_eq!(inflate(&deflate(&noise)), noise);
+    }
+
+    #[test]
+    fn rgb_round_trip() {
+        let image = image(255);
+        let (color_type, samples) = decode(&encode(&image));
+
+        assert_eq!(color_type, 2);
+        assert_eq!(samples, image.pixels().iter().flat_map(|c| [c.red, c.green, c.blue]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rgba_round_trip() {
+        let image = image(100);
+        let (color_type, samples) = decode(&encode(&image));
+
+        assert_eq!(color_type, 6);
+        assert_eq!(samples, image.pixels().iter().flat_map(|c| [c.red, c.green, c.blue, c.alpha]).collect::<Vec<_>>());
+    }
+}
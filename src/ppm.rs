@@ -0,0 +1,125 @@
+use std::io;
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::new_color;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PpmFormat {
+    // P3
+    Ascii,
+    // P6
+    #[default]
+    Binary,
+}
+
+// ppm has no alpha channel, so it is dropped
+pub fn encode(image: &Image, format: PpmFormat) -> Vec<u8> {
+    match format {
+        PpmFormat::Ascii => format!("{}", image).into_bytes(),
+        PpmFormat::Binary => {
+            let mut out = format!("P6\n{} {}\n255\n", image.width(), image.height()).into_bytes();
+            out.reserve(image.width() * image.height() * 3);
+            for color in image.pixels().iter() {
+                out.extend_from_slice(&[color.red, color.green, color.blue]);
+            }
+
+            out
+        },
+    }
+}
+
+// reads P2, P3, P5 and P6, scaling samples to 8 bits and spreading gray across all three channels
+pub fn decode(data: &[u8]) -> io::Result<Image> {
+    let mut reader = Reader { data, pos: 0 };
+
+    let magic = reader.token()?;
+    let (channels, binary) = match magic {
+        b"P2" => (1, false),
+        b"P3" => (3, false),
+        b"P5" => (1, true),
+        b"P6" => (3, true),
+        _ => return Err(invalid("not a P2, P3, P5 or P6 file")),
+    };
+
+    let width = reader.number()?;
+    let height = reader.number()?;
+    let maxval = reader.number()?;
+    if !(1..=65535).contains(&maxval) {
+        return Err(invalid("maxval must be between 1 and 65535"));
+    }
+
+    let count = width.checked_mul(height).and_then(|n| n.checked_mul(channels)).ok_or_else(|| invalid("image is too large"))?;
+    let short = || invalid("raster is shorter than the header says");
+
+    // the header alone can claim any size, so nothing is reserved past what the file could actually hold
+    let samples = if binary {
+        // exactly one whitespace byte separates the header from the raster
+        reader.pos += 1;
+        let size = if maxval < 256 { 1 } else { 2 };
+        let end = count.checked_mul(size).and_then(|n| n.checked_add(reader.pos)).ok_or_else(short)?;
+        let raster = data.get(reader.pos..end).ok_or_else(short)?;
+
+        raster.chunks(size).map(|sample| sample.iter().fold(0usize, |v, &b| v << 8 | b as usize)).collect::<Vec<_>>()
+    } else {
+        let mut samples = Vec::with_capacity(count.min(data.len().saturating_sub(reader.pos)));
+        for _ in 0..count {
+            samples.push(reader.number()?);
+        }
+
+        samples
+    };
+
+    if samples.iter().any(|&s| s > maxval) {
+        return Err(invalid("sample is larger than maxval"));
+    }
+
+    let scale = |s: usize| ((s * 255 + maxval / 2) / maxval) as u8;
+    let pixels = samples.chunks(channels).map(|p| {
+        let [r, g, b] = if channels == 1 { [p[0]; 3] } else { [p[0], p[1], p[2]] };
+        new_color!(scale(r), scale(g), scale(b))
+    }).collect::<Vec<Color>>();
+
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    // skips whitespace and # comments before the next token
+    fn token(&mut self) -> io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                },
+                Some(_) => break,
+                None => return Err(invalid("unexpected end of file")),
+            }
+        }
+
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        let token = self.token()?;
+        std::str::from_utf8(token).ok()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid(&format!("expected a number, found '{}'", String::from_utf8_lossy(token))))
+    }
+}
@@ -0,0 +1,108 @@
+// how the ends of a wide edge are finished
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    // stops flush with the endpoint
+    #[default]
+    Butt,
+    Round,
+    // runs half the width past the endpoint
+    Square,
+}
+
+// how two wide edges meeting end to start are connected
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    // falls back to a bevel once the point grows past MITER_LIMIT half widths
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+pub const MITER_LIMIT: f64 = 4f64;
+
+// how draw_lines renders each edge; caps, joins and antialiasing only apply above and at one pixel of width respectively
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineStyle {
+    // blends partial pixel coverage into the image instead of stepping whole pixels
+    pub antialias: bool,
+    // in pixels
+    pub width: f64,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    // alternating drawn and skipped lengths in pixels, empty for a solid line
+    pub dash: Vec<f64>,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            antialias: false,
+            width: 1f64,
+            cap: LineCap::default(),
+            join: LineJoin::default(),
+            dash: Vec::new(),
+        }
+    }
+}
+
+impl LineStyle {
+    pub fn is_wide(&self) -> bool {
+        self.width > 1f64
+    }
+
+    // an odd count of lengths swaps drawn and skipped on every other pass, just like svg
+    fn pattern(&self) -> Option<Vec<f64>> {
+        if self.dash.iter().sum::<f64>() <= 0f64 {
+            return None;
+        }
+
+        Some(match self.dash.len() % 2 {
+            0 => self.dash.clone(),
+            _ => [self.dash.as_slice(), self.dash.as_slice()].concat(),
+        })
+    }
+
+    // whether the point this far into the pattern falls in a drawn stretch
+    pub fn is_drawn(&self, distance: f64) -> bool {
+        let pattern = match self.pattern() {
+            Some(pattern) => pattern,
+            None => return true,
+        };
+
+        let mut position = distance % pattern.iter().sum::<f64>();
+        for (i, &length) in pattern.iter().enumerate() {
+            if position < length {
+                return i % 2 == 0;
+            }
+            position -= length;
+        }
+
+        false
+    }
+
+    // the drawn stretches between start and end, as distances along an edge whose pattern begins at phase
+    pub fn dashes(&self, phase: f64, start: f64, end: f64) -> Vec<(f64, f64)> {
+        let pattern = match self.pattern() {
+            Some(pattern) => pattern,
+            None => return vec![(start, end)],
+        };
+
+        let period = pattern.iter().sum::<f64>();
+        let mut position = start - (phase + start) % period;
+        let mut dashes = Vec::new();
+
+        for (i, &length) in pattern.iter().enumerate().cycle() {
+            if position > end {
+                break;
+            }
+
+            if i % 2 == 0 && position + length >= start {
+                dashes.push((position.max(start), (position + length).min(end)));
+            }
+            position += length;
+        }
+
+        dashes
+    }
+}
@@ -0,0 +1,144 @@
+use pepper::color::Color;
+use pepper::gif::GifEncoder;
+use pepper::image::Image;
+
+// frames with their local color tables looked up, following the layout the encoder writes
+fn decode(data: &[u8]) -> (usize, usize, Vec<Vec<Color>>) {
+    assert_eq!(&data[..6], b"GIF89a");
+    let width = u16::from_le_bytes([data[6], data[7]]) as usize;
+    let height = u16::from_le_bytes([data[8], data[9]]) as usize;
+
+    let mut pos = 13;
+    let mut frames = Vec::new();
+    loop {
+        match data[pos] {
+            0x21 => {
+                pos += 2;
+                while data[pos] != 0 {
+                    pos += data[pos] as usize + 1;
+                }
+                pos += 1;
+            },
+            0x2c => {
+                let flags = data[pos + 9];
+                assert!(flags & 0x80 != 0, "frame has no local color table");
+                pos += 10;
+
+                let entries = 1 << ((flags & 7) + 1);
+                let palette = data[pos..pos + entries * 3].chunks(3).map(|c| Color { red: c[0], green: c[1], blue: c[2], alpha: 255 }).collect::<Vec<_>>();
+                pos += entries * 3;
+
+                let min_code_size = data[pos];
+                pos += 1;
+                let mut stream = Vec::new();
+                while data[pos] != 0 {
+                    let len = data[pos] as usize;
+                    stream.extend_from_slice(&data[pos + 1..pos + 1 + len]);
+                    pos += len + 1;
+                }
+                pos += 1;
+
+                let indices = lzw(&stream, min_code_size);
+                assert_eq!(indices.len(), width * height);
+                frames.push(indices.iter().map(|&i| palette[i as usize]).collect());
+            },
+            0x3b => return (width, height, frames),
+            block => panic!("unexpected block {:#x}", block),
+        }
+    }
+}
+
+fn lzw(stream: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1usize << min_code_size;
+    let end = clear + 1;
+    let reset = || (0..clear).map(|i| vec![i as u8]).chain([Vec::new(), Vec::new()]).collect::<Vec<_>>();
+
+    let mut table = reset();
+    let mut size = min_code_size + 1;
+    let mut previous: Option<Vec<u8>> = None;
+    let mut out = Vec::new();
+    let (mut bits, mut held, mut pos) = (0u32, 0u8, 0);
+
+    loop {
+        while held < size {
+            bits |= (stream[pos] as u32) << held;
+            held += 8;
+            pos += 1;
+        }
+        let code = (bits & ((1 << size) - 1)) as usize;
+        bits >>= size;
+        held -= size;
+
+        if code == clear {
+            table = reset();
+            size = min_code_size + 1;
+            previous = None;
+            continue;
+        }
+        if code == end {
+            return out;
+        }
+
+        let entry = match (&previous, table.get(code)) {
+            (_, Some(entry)) => entry.clone(),
+            (Some(previous), None) => [previous.clone(), vec![previous[0]]].concat(),
+            (None, None) => panic!("code {} before anything was decoded", code),
+        };
+
+        if let Some(previous) = previous {
+            if table.len() < 4096 {
+                table.push([previous, vec![entry[0]]].concat());
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+        }
+
+        out.extend_from_slice(&entry);
+        previous = Some(entry);
+    }
+}
+
+fn image(width: usize, height: usize, pixel: impl Fn(usize, usize) -> Color) -> Image {
+    let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| pixel(x, y)).collect();
+    Image::from_pixels(width, height, pixels)
+}
+
+// no more colors than fit in a palette, so nothing gets quantized away
+#[test]
+fn exact_colors_round_trip() {
+    let frames = [
+        image(40, 30, |x, y| Color { red: (x % 8 * 30) as u8, green: (y % 8 * 30) as u8, blue: ((x + y) % 4 * 80) as u8, alpha: 255 }),
+        image(40, 30, |x, _| Color { red: 0, green: 0, blue: (x % 3 * 100) as u8, alpha: 255 }),
+    ];
+
+    let (width, height, decoded) = decode(&GifEncoder::default().encode(&frames).unwrap());
+    assert_eq!((width, height), (40, 30));
+    assert_eq!(decoded.len(), 2);
+    for (frame, pixels) in frames.iter().zip(&decoded) {
+        assert_eq!(*frame.pixels(), pixels[..]);
+    }
+}
+
+// scattered colors, far more than fit in a palette, with uneven ties along every channel
+#[test]
+fn quantizing_is_deterministic() {
+    let mut seed = 1u32;
+    let pixels = (0..128 * 128).map(|_| {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        let r = seed >> 8;
+        Color { red: (r % 97) as u8 * 2, green: (r / 97 % 89) as u8, blue: (r / 8633 % 60) as u8 * 4, alpha: 255 }
+    }).collect();
+    let frame = Image::from_pixels(128, 128, pixels);
+    let encoded = GifEncoder::default().encode(std::slice::from_ref(&frame)).unwrap();
+
+    for _ in 0..2 {
+        assert!(GifEncoder::default().encode(std::slice::from_ref(&frame)).unwrap() == encoded, "the same image encoded differently");
+    }
+
+    let (_, _, decoded) = decode(&encoded);
+    let worst = frame.pixels().iter().zip(&decoded[0]).map(|(a, b)| {
+        [a.red.abs_diff(b.red), a.green.abs_diff(b.green), a.blue.abs_diff(b.blue)].into_iter().max().unwrap()
+    }).max().unwrap();
+    assert!(worst < 48, "a pixel ended up {} away from its own color", worst);
+}
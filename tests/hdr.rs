@@ -0,0 +1,32 @@
+use pepper::color::COLOR_BLACK;
+use pepper::image::Image;
+use pepper::mdl::{parse, Interpreter};
+
+// an ambient-lit surface and a line in the same gray have to come out the same, tone mapped or not
+fn gray_pixels(mode: &str) -> (u8, u8) {
+    let source = format!("hdr {}
+ambient 50 50 50
+constants gray 1 0 0 1 0 0 1 0 0
+sphere gray 50 60 0 30
+color 50 50 50
+line 0 5 0 99 5 0
+", mode);
+
+    let mut image = Image::new(100, 100, COLOR_BLACK);
+    Interpreter::default().run(&mut image, &parse(&source).unwrap()).unwrap();
+
+    let pixels = image.pixels();
+    let surface = pixels[50 + (99 - 60) * 100];
+    let line = pixels[50 + (99 - 5) * 100];
+    assert_eq!((surface.red, surface.green), (surface.blue, surface.blue));
+
+    (surface.red, line.red)
+}
+
+#[test]
+fn ambient_matches_lines() {
+    for mode in ["off", "reinhard", "aces"] {
+        let (surface, line) = gray_pixels(mode);
+        assert!(surface.abs_diff(line) <= 1, "hdr {}: surface is {} but the line is {}", mode, surface, line);
+    }
+}
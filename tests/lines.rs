@@ -0,0 +1,38 @@
+use pepper::color::{Color, COLOR_BLACK, COLOR_PASTEL_YELLOW, COLOR_RED, COLOR_WHITE};
+use pepper::image::Image;
+use pepper::mdl::{parse, Interpreter};
+
+fn render(source: &str) -> Image {
+    let mut image = Image::new(100, 100, COLOR_BLACK);
+    Interpreter::default().run(&mut image, &parse(source).unwrap()).unwrap();
+    image
+}
+
+// anything short of the far edge still truncates onto the last row or column
+#[test]
+fn last_row_and_column() {
+    let image = render("color white\nline 0 99.5 0 99 99.5 0\nline 99.5 0 0 99.5 99 0\n");
+    let pixels = image.pixels();
+
+    // y counts up from the bottom, so row 99 is the first one stored
+    assert!((0..100).all(|x| pixels[x] == COLOR_WHITE), "row 99 is not fully drawn");
+    assert!((0..100).all(|y| pixels[99 + y * 100] == COLOR_WHITE), "column 99 is not fully drawn");
+}
+
+// a shape's own color word wins over the draw color, with or without a constants name before it
+#[test]
+fn wireframe_shape_colors() {
+    let image = render("wireframe on\nconstants shiny 0.2 0.5 0.8 0.2 0.5 0.8 0.2 0.5 0.8
+box red 10 90 0 30 30 30\nsphere shiny lime 70 30 0 20\n");
+    let pixels = image.pixels();
+
+    assert!(pixels.contains(&COLOR_RED));
+    assert!(pixels.contains(&Color { red: 0, green: 255, blue: 0, alpha: 255 }));
+    assert!(!pixels.contains(&COLOR_PASTEL_YELLOW));
+
+    // filled shapes still light with constants and only fall back to a color when no constants have that name
+    render("box red 10 90 0 30 30 30\n");
+    let mut image = Image::new(100, 100, COLOR_BLACK);
+    let error = Interpreter::default().run(&mut image, &parse("box nothing 10 90 0 30 30 30\n").unwrap()).unwrap_err();
+    assert!(error.to_string().contains("unknown constants 'nothing'"));
+}
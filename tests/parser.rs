@@ -0,0 +1,29 @@
+use pepper::color::Color;
+use pepper::lighting::LightSource;
+use pepper::mdl::{parse, Command};
+
+// the numbers after a light's three channels are its location, never an alpha
+#[test]
+fn numeric_light() {
+    let program = parse("light 255 128 0 1 2 3\nlight point 10 20 30 100 200 300\n").unwrap();
+    let lights = program.iter().map(|statement| match &statement.command {
+        Command::Light(light) => (light.color, light.source),
+        command => panic!("expected a light, found {:?}", command),
+    }).collect::<Vec<_>>();
+
+    assert_eq!(lights[0].0, Color { red: 255, green: 128, blue: 0, alpha: 255 });
+    assert!(matches!(lights[0].1, LightSource::Directional([1.0, 2.0, 3.0])));
+    assert_eq!(lights[1].0, Color { red: 10, green: 20, blue: 30, alpha: 255 });
+    assert!(matches!(lights[1].1, LightSource::Point([100.0, 200.0, 300.0])));
+}
+
+// sizes from the script are checked before anything is allocated for them
+#[test]
+fn oversized_filters() {
+    assert!(parse("convolve 99999 99999 1\n").is_err());
+    assert!(parse("convolve 99999999999 99999999999 1\n").is_err());
+    assert!(parse("convolve 3 3 1 1 1\n").is_err());
+    assert!(parse("median 100000\n").is_err());
+
+    assert!(parse("convolve 3 1 0.25 0.5 0.25\nmedian 2\n").is_ok());
+}
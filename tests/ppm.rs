@@ -0,0 +1,45 @@
+use pepper::color::Color;
+use pepper::image::Image;
+use pepper::ppm::{decode, encode, PpmFormat};
+
+fn gray(value: u8) -> Color {
+    Color { red: value, green: value, blue: value, alpha: 255 }
+}
+
+fn error(data: &[u8]) -> String {
+    decode(data).err().expect("decoding should have failed").to_string()
+}
+
+#[test]
+fn round_trip() {
+    let pixels = (0..7 * 5).map(|i| Color { red: (i * 7) as u8, green: 255 - i as u8, blue: (i % 3 * 127) as u8, alpha: 255 }).collect::<Vec<_>>();
+    let image = Image::from_pixels(7, 5, pixels);
+
+    for format in [PpmFormat::Binary, PpmFormat::Ascii] {
+        let decoded = decode(&encode(&image, format)).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (7, 5));
+        assert_eq!(decoded.pixels(), image.pixels(), "{:?} did not round trip", format);
+    }
+}
+
+// gray spreads over all three channels and samples scale from maxval to 255
+#[test]
+fn gray_and_maxval() {
+    let image = decode(b"P2\n# a comment\n3 1\n15\n0 15 # another\n 7\n").unwrap();
+    assert_eq!(*image.pixels(), [gray(0), gray(255), gray(119)]);
+
+    let image = decode(b"P5\n3 1\n65535\n\x00\x00\xff\xff\x80\x00").unwrap();
+    assert_eq!(*image.pixels(), [gray(0), gray(255), gray(128)]);
+}
+
+#[test]
+fn bad_input() {
+    assert!(error(b"P6\n2 2\n255\n\x01\x02\x03").contains("shorter than the header"));
+    assert!(error(b"P5\n2 1\n65535\n\x00\x00\x01").contains("shorter than the header"));
+    assert!(error(b"P6\n100000 100000\n255\n").contains("shorter than the header"));
+    assert!(error(b"P3\n2 1\n255\n1 2 3 4 5").contains("end of file"));
+    assert!(error(b"P2\n2 1\n100\n50 101\n").contains("larger than maxval"));
+    assert!(error(b"P5\n1 1\n255\n").contains("shorter than the header"));
+    assert!(error(b"P7\n1 1\n255\n").contains("not a P2"));
+    assert!(error(b"P6\n1 1\n0\n").contains("maxval"));
+}
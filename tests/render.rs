@@ -0,0 +1,36 @@
+use std::env;
+
+use pepper::color::COLOR_BLACK;
+use pepper::image::Image;
+use pepper::mdl::{parse, Interpreter};
+
+// channel differences this small come from floating point noise between platforms, not from real changes
+const TOLERANCE: u8 = 2;
+
+// renders tests/scripts/<name>.mdl and checks it against tests/reference/<name>.ppm;
+// set PEPPER_BLESS to write the reference from the current output instead
+fn check(name: &str) {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let source = std::fs::read_to_string(format!("{}/tests/scripts/{}.mdl", root, name)).unwrap();
+    let reference = format!("{}/tests/reference/{}.ppm", root, name);
+
+    let mut image = Image::new(500, 500, COLOR_BLACK);
+    Interpreter::default().run(&mut image, &parse(&source).unwrap()).unwrap();
+
+    if env::var_os("PEPPER_BLESS").is_some() {
+        image.save(&reference).unwrap();
+        return;
+    }
+
+    let comparison = image.compare_within(&Image::open(&reference).unwrap(), TOLERANCE);
+    if !comparison.is_match() {
+        let diff = format!("{}/target/{}-diff.png", root, name);
+        comparison.diff.save(&diff).unwrap();
+        panic!("{} does not match its reference: {}, see {}", name, comparison, diff);
+    }
+}
+
+#[test]
+fn shapes() {
+    check("shapes");
+}